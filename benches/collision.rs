@@ -0,0 +1,70 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use runnner::scenes::game::bench::{self, Hitbox};
+use runnner::scenes::game::{Input, Simulation};
+use std::hint::black_box;
+
+// levels of growing size, collision should cost the same however long the
+// level is
+const SCREENS: [i32; 5] = [4, 16, 64, 256, 1024];
+// a few seconds of running right and jumping now and then
+const STEPS: u32 = 300;
+// about one step of Mario running and falling
+const MOVE: (f32, f32) = (0.02, -0.03);
+
+fn simulation(screens: i32) -> Simulation {
+    bench::simulation(screens).expect("the bench level parses")
+}
+
+fn mario(simulation: &Simulation) -> Hitbox {
+    let spirit = &simulation.spirit;
+    Hitbox{x: spirit.x, y: spirit.y, h: spirit.h, w: spirit.w}
+}
+
+// Looking up the solids around one move.
+fn query(c: &mut Criterion) {
+    let mut group = c.benchmark_group("query");
+    for screens in SCREENS {
+        let simulation = simulation(screens);
+        let area = bench::swept_area(mario(&simulation), MOVE.0, MOVE.1);
+        group.bench_with_input(BenchmarkId::from_parameter(screens), &area, |b, area| {
+            b.iter(|| simulation.world.solids.query(black_box(*area)));
+        });
+    }
+    group.finish();
+}
+
+// One move of Mario through the solids around it, as the simulation does it.
+fn move_and_slide(c: &mut Criterion) {
+    let mut group = c.benchmark_group("move_and_slide");
+    for screens in SCREENS {
+        let simulation = simulation(screens);
+        let hitbox = mario(&simulation);
+        group.bench_with_input(BenchmarkId::from_parameter(screens), &hitbox, |b, hitbox| {
+            b.iter(|| {
+                let area = bench::swept_area(*hitbox, MOVE.0, MOVE.1);
+                let nearby: Vec<Hitbox> = simulation.world.solids.query(area).iter().map(|(solid, _)| *solid).collect();
+                bench::move_and_slide(black_box(*hitbox), MOVE.0, MOVE.1, &nearby)
+            });
+        });
+    }
+    group.finish();
+}
+
+// Whole steps, collision and everything else a step does.
+fn step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step");
+    for screens in SCREENS {
+        group.bench_with_input(BenchmarkId::from_parameter(screens), &screens, |b, &screens| {
+            b.iter_batched(|| simulation(screens), |mut simulation| {
+                for step in 0..STEPS {
+                    simulation.step(&Input{right: true, up: step%40 == 0, ..Default::default()});
+                }
+                simulation
+            }, BatchSize::LargeInput);
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, query, move_and_slide, step);
+criterion_main!(benches);
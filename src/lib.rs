@@ -0,0 +1,3 @@
+// The game as a library, the binary and the benchmarks are built on it.
+pub mod scenes;
+pub mod render;
@@ -1,12 +1,47 @@
 extern crate sdl2;
 
-mod scenes;
-mod render;
-
+use runnner::scenes;
 use sdl2::video::GLProfile;
 use sdl2::keyboard::Keycode;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 2 && args[1] == "--check-level" {
+        let failed = scenes::game::check_levels(&args[2..]);
+        std::process::exit(if failed == 0 {0} else {1});
+    }
+    if args.len() == 3 && args[1] == "--export-map" {
+        let ok = scenes::game::export_map(&args[2]);
+        std::process::exit(if ok {0} else {1});
+    }
+    if args.len() == 3 && args[1] == "--run-replay" {
+        let ok = match scenes::game::Replay::load(&args[2]) {
+            Ok(replay) => scenes::game::run_replay(&replay),
+            Err(message) => {
+                eprintln!("{}", message);
+                false
+            },
+        };
+        std::process::exit(if ok {0} else {1});
+    }
+
+    // `--record <file>` saves the inputs of the first game, `--replay <file>`
+    // plays a saved game back instead of reading the keyboard
+    let mut record_path: Option<String> = None;
+    let mut playback: Option<scenes::game::Replay> = None;
+    if args.len() == 3 && args[1] == "--record" {
+        record_path = Some(args[2].clone());
+    }
+    if args.len() == 3 && args[1] == "--replay" {
+        match scenes::game::Replay::load(&args[2]) {
+            Ok(replay) => playback = Some(replay),
+            Err(message) => {
+                eprintln!("{}", message);
+                std::process::exit(1);
+            },
+        }
+    }
+
     let sdl_context = sdl2::init().unwrap();
     let timer_subsystem = sdl_context.timer().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
@@ -23,15 +58,23 @@ fn main() {
     gl::load_with(|name| video_subsystem.gl_get_proc_address(name) as *const _);
 
     let mut event_pump = sdl_context.event_pump().unwrap();
-    let mut game = scenes::game::Game::init();
+    // a replay goes straight into the game
+    let first: Box<dyn scenes::Scene> = match &playback {
+        Some(replay) => Box::new(scenes::game::Game::from_replay(replay)),
+        None => Box::new(scenes::title::Title::init(scenes::game::LEVEL_PATH, record_path)),
+    };
+    let mut stack = scenes::Scenes::init(first);
     let mut up = false;
     let mut down = false;
     let mut right = false;
     let mut left = false;
+    let mut fire = false;
+    let mut pause = false;
 
-    let mut sec = 0;
+    // time not yet simulated, it's worked off in fixed steps
+    let mut accumulator = 0.0;
+    let mut last_ticks = timer_subsystem.ticks();
     'main: loop {
-        let last_ticks = timer_subsystem.ticks();
         for event in event_pump.poll_iter() {
             match event {
                 sdl2::event::Event::Quit {..} => break 'main,
@@ -41,6 +84,8 @@ fn main() {
                         Keycode::A => left = true,
                         Keycode::W => up = true,
                         Keycode::S => down = true,
+                        Keycode::Space => fire = true,
+                        Keycode::Escape => pause = true,
                         _ => {},
                     }
                 },
@@ -50,34 +95,35 @@ fn main() {
                         Keycode::A => left = false,
                         Keycode::W => up = false,
                         Keycode::S => down = false,
+                        Keycode::Space => fire = false,
+                        Keycode::Escape => pause = false,
                         _ => {},
                     }
                 },
                 _ => {},
             }
         }
-        //if !game.spirit.is_dead {
-            if left {game.move_x("left")};
-            if right {game.move_x("right")};
-            if up {game.jump()};
-            if down {game.crouch()};
-        //}
-
-        unsafe{game.draw()};
-        window.gl_swap_window();
-
-        let deltatime = timer_subsystem.ticks()-last_ticks;
+        let keyboard = scenes::game::Input{left, right, up, down, fire, pause};
 
-        sec += deltatime;
-        if sec > 1000 {
-            sec = 0;
-            game.time -= 1;
+        let ticks = timer_subsystem.ticks();
+        accumulator += (ticks-last_ticks) as f32;
+        last_ticks = ticks;
+        // after a stall skip ahead instead of simulating all of it at once
+        if accumulator > 250.0 {
+            accumulator = 250.0;
         }
 
-        if game.is_endlvl {
-            game.endLevel(deltatime);
-        }else {
-            game.handle(deltatime);
+        while accumulator >= scenes::game::STEP_MS {
+            stack.step(&keyboard);
+            accumulator -= scenes::game::STEP_MS;
         }
+        if stack.is_empty() {
+            break 'main;
+        }
+
+        unsafe{stack.draw(accumulator/scenes::game::STEP_MS)};
+        window.gl_swap_window();
     }
-}
\ No newline at end of file
+
+    stack.quit();
+}
@@ -5,7 +5,6 @@ use std;
 use std::ffi::{CString, CStr, c_void};
 use std::path::Path;
 use std::collections::HashMap;
-use crate::scenes::game;
 
 pub struct Texts {
     texture: Texture,
@@ -21,7 +20,7 @@ impl Texts {
         
         let mut y_bitmap = 0;
         let mut i = 0;
-        for mut j in 0..=37 {
+        for _ in 0..=37 {
             if i == 16 {
                 y_bitmap += 1;
                 i = 0;
@@ -39,8 +38,7 @@ impl Texts {
             i += 1;
         }
 
-        let bitmap: HashMap<_, _> = chars.iter().zip(positions.iter()).collect();
-        let texture = Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/font_bitmap.png"));
+        let texture = Texture::create_new_texture_from_file(Path::new("src/scenes/game/assets/images/font_bitmap.png"));
         
         let vert_shader = Shader::vertex_from_src(
             &CString::new(include_str!("scenes/game/assets/shaders/text.vert")).unwrap(),
@@ -154,10 +152,6 @@ impl Texture {
 pub struct Object {
     pub vao: gl::types::GLuint,
     pub vbo: gl::types::GLuint,
-    pub x: f32,
-    pub y: f32,
-    pub h: f32,
-    pub w: f32,
 }
 
 impl Object {
@@ -207,22 +201,12 @@ impl Object {
             gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
         }
 
-        // default options for objects that use collision by being Block class
-        let x = -1.0;
-        let y = -1.0;
-        let h = 0.0;
-        let w = 0.0;
-
-        Self{vao, vbo, x, y, h, w}
-    }
-
-    pub fn set_cordinates(&mut self, x: f32, y: f32, h: f32, w: f32) {
-        self.x = x;
-        self.y = y;
-        self.h = h;
-        self.w = w;
+        Self{vao, vbo}
     }
 
+    /// # Safety
+    /// The GL context the object was created in has to be current, and `pointer`
+    /// has to be an offset into its buffer that fits the attribute.
     pub unsafe fn set_vertex_attrib_pointer(
         &self,
         loc: gl::types::GLuint, 
@@ -294,6 +278,39 @@ impl Program {
             gl::UseProgram(self.program);
         }
     }
+
+    fn uniform_location(&self, name: &str) -> gl::types::GLint {
+        let cname = CString::new(name).expect("CString::new failed");
+        unsafe { gl::GetUniformLocation(self.program, cname.as_ptr()) }
+    }
+
+    /// # Safety
+    /// The GL context the program was created in has to be current.
+    pub unsafe fn set_uniform_1i(&self, name: &str, value: i32) {
+        self.set_active();
+        gl::Uniform1i(self.uniform_location(name), value);
+    }
+
+    /// # Safety
+    /// The GL context the program was created in has to be current.
+    pub unsafe fn set_uniform_1f(&self, name: &str, value: f32) {
+        self.set_active();
+        gl::Uniform1f(self.uniform_location(name), value);
+    }
+
+    /// # Safety
+    /// The GL context the program was created in has to be current.
+    pub unsafe fn set_uniform_2f(&self, name: &str, x: f32, y: f32) {
+        self.set_active();
+        gl::Uniform2f(self.uniform_location(name), x, y);
+    }
+
+    /// # Safety
+    /// The GL context the program was created in has to be current.
+    pub unsafe fn set_uniform_mat4(&self, name: &str, mat: &glm::Mat4) {
+        self.set_active();
+        gl::UniformMatrix4fv(self.uniform_location(name), 1, gl::FALSE, &mat[0][0]);
+    }
 }
 
 impl Drop for Program {
@@ -1 +1,92 @@
-pub mod game;
\ No newline at end of file
+pub mod game;
+pub mod title;
+pub mod intermission;
+pub mod game_over;
+pub mod options;
+
+use game::{Input, Renderer};
+
+// What a scene wants done with the stack after it was stepped.
+pub enum Transition {
+    None,
+    Push(Box<dyn Scene>),
+    Replace(Box<dyn Scene>),
+    Pop,
+}
+
+// A screen of the game. Each fixed step the scene on top gets the keys held
+// down, then it's updated and asked whether it wants to make way.
+pub trait Scene {
+    fn handle_input(&mut self, input: &Input);
+    fn update(&mut self);
+    /// `alpha` is how far the next step is along, from 0 to 1.
+    ///
+    /// # Safety
+    /// The GL context `renderer` was created in has to be current.
+    unsafe fn draw(&mut self, renderer: &mut Renderer, alpha: f32);
+
+    fn transition(&mut self) -> Transition {
+        Transition::None
+    }
+
+    // the window is closing
+    fn quit(&mut self) {}
+}
+
+// Only the scene on top runs and is drawn, the ones below wait until it
+// pops. All of them draw with the same renderer.
+pub struct Scenes {
+    stack: Vec<Box<dyn Scene>>,
+    renderer: Renderer,
+}
+
+impl Scenes {
+    pub fn init(first: Box<dyn Scene>) -> Self {
+        let mut scenes = Self{stack: vec![first], renderer: Renderer::init()};
+        scenes.apply_transitions();
+        scenes
+    }
+
+    pub fn step(&mut self, input: &Input) {
+        if let Some(top) = self.stack.last_mut() {
+            top.handle_input(input);
+            top.update();
+        }
+        self.apply_transitions();
+    }
+
+    // a scene that was just pushed or uncovered may move on right away
+    fn apply_transitions(&mut self) {
+        while let Some(top) = self.stack.last_mut() {
+            match top.transition() {
+                Transition::None => break,
+                Transition::Push(scene) => self.stack.push(scene),
+                Transition::Replace(scene) => {
+                    self.stack.pop();
+                    self.stack.push(scene);
+                },
+                Transition::Pop => {
+                    self.stack.pop();
+                },
+            }
+        }
+    }
+
+    /// # Safety
+    /// The GL context the stack's renderer was created in has to be current.
+    pub unsafe fn draw(&mut self, alpha: f32) {
+        if let Some(top) = self.stack.last_mut() {
+            top.draw(&mut self.renderer, alpha);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    pub fn quit(&mut self) {
+        for scene in self.stack.iter_mut().rev() {
+            scene.quit();
+        }
+    }
+}
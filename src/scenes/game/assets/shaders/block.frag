@@ -1,10 +0,0 @@
-#version 330 core
-out vec4 FragColor;  
-in vec2 texPos;  
-
-uniform sampler2D texture0;
-
-void main()
-{
-    FragColor = texture(texture0, texPos);
-}
\ No newline at end of file
@@ -3,8 +3,34 @@ out vec4 FragColor;
 in vec2 texPos;  
 
 uniform sampler2D texture0;
+// 0 normally, 1 to 3 picks the palette while Mario has a star
+uniform int starPhase;
+// 1 normally, lower while the screen fades to black
+uniform float brightness;
+
+// the colours of Mario's hat, hair and fire suit that a star swaps out
+const vec3 RED = vec3(181.0, 49.0, 32.0)/255.0;
+const vec3 OLIVE = vec3(107.0, 109.0, 0.0)/255.0;
+const vec3 WHITE = vec3(255.0, 254.0, 255.0)/255.0;
+
+const vec3 PALETTES[9] = vec3[9](
+    vec3(12.0, 147.0, 0.0)/255.0, vec3(0.0, 0.0, 0.0), vec3(255.0, 254.0, 255.0)/255.0,
+    vec3(255.0, 254.0, 255.0)/255.0, vec3(181.0, 49.0, 32.0)/255.0, vec3(234.0, 158.0, 34.0)/255.0,
+    vec3(0.0, 0.0, 0.0), vec3(234.0, 158.0, 34.0)/255.0, vec3(12.0, 147.0, 0.0)/255.0
+);
 
 void main()
 {
-    FragColor = texture(texture0, texPos);
-}
\ No newline at end of file
+    vec4 color = texture(texture0, texPos);
+    if (starPhase > 0) {
+        vec3 from[3] = vec3[3](RED, OLIVE, WHITE);
+        for (int i = 0; i < 3; i++) {
+            if (distance(color.rgb, from[i]) < 0.02) {
+                color.rgb = PALETTES[(starPhase-1)*3+i];
+                break;
+            }
+        }
+    }
+    color.rgb *= brightness;
+    FragColor = color;
+}
@@ -3,8 +3,11 @@ out vec4 FragColor;
 in vec2 texPos;  
 
 uniform sampler2D texture0;
+// 1 normally, lower while the screen fades to black
+uniform float brightness;
 
 void main()
 {
     FragColor = texture(texture0, texPos);
+    FragColor.rgb *= brightness;
 }
\ No newline at end of file
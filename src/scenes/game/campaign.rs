@@ -0,0 +1,26 @@
+// The levels in the order they are played, finishing one goes on to the
+// next. A level belongs to the stage whose name it has, see `name` in
+// level.rs, any other level is played on its own.
+pub struct Stage {
+    pub world: u32,
+    pub level: u32,
+    pub path: &'static str,
+}
+
+pub const STAGES: [Stage; 5] = [
+    Stage{world: 1, level: 1, path: "src/scenes/game/assets/levels/1-1.map"},
+    Stage{world: 1, level: 2, path: "src/scenes/game/assets/levels/1-2.map"},
+    Stage{world: 1, level: 3, path: "src/scenes/game/assets/levels/1-3.map"},
+    Stage{world: 1, level: 4, path: "src/scenes/game/assets/levels/1-4.map"},
+    Stage{world: 2, level: 1, path: "src/scenes/game/assets/levels/2-1.map"},
+];
+
+impl Stage {
+    pub fn name(&self) -> String {
+        format!("{}-{}", self.world, self.level)
+    }
+}
+
+pub fn find(name: &str) -> Option<usize> {
+    STAGES.iter().position(|stage| stage.name() == name)
+}
@@ -0,0 +1,319 @@
+use crate::scenes::game::level;
+use std::collections::HashMap;
+
+// Axis aligned boxes, positions are the center and sizes are half extents
+// like everywhere else in the game.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Hitbox {
+    pub x: f32,
+    pub y: f32,
+    pub h: f32,
+    pub w: f32,
+}
+
+// The side of the first box that touches the second one.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Side {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+pub struct Contact {
+    pub side: Side,
+    // how far the boxes overlap along the normal, for a move that was
+    // stopped how far it would have gone in
+    pub depth: f32,
+    // the direction that moves the first box out of the second one
+    pub normal: (f32, f32),
+}
+
+pub trait Collider {
+    fn hitbox(&self) -> Hitbox;
+
+    fn contact(&self, other: &dyn Collider) -> Option<Contact> {
+        contact(self.hitbox(), other.hitbox())
+    }
+}
+
+// Boxes that only touch count as a contact with no depth. The other box is
+// above only if our center is within its width, otherwise it's beside us.
+pub fn contact(a: Hitbox, b: Hitbox) -> Option<Contact> {
+    let overlap = a.x+a.w >= b.x-b.w
+        && b.x+b.w >= a.x-a.w
+        && a.y+a.h >= b.y-b.h
+        && b.y+b.h >= a.y-a.h;
+    if !overlap {
+        return None;
+    }
+
+    let side = if b.y < a.y {
+        Side::Bottom
+    }else if b.y > a.y && b.x-b.w < a.x && a.x < b.x+b.w {
+        Side::Top
+    }else if b.x < a.x {
+        Side::Left
+    }else if b.x > a.x {
+        Side::Right
+    }else {
+        return None;
+    };
+
+    let normal = match side {
+        Side::Bottom => (0.0, 1.0),
+        Side::Top => (0.0, -1.0),
+        Side::Left => (1.0, 0.0),
+        Side::Right => (-1.0, 0.0),
+    };
+
+    let depth = depth(a, b, side);
+    Some(Contact{side, depth, normal})
+}
+
+// How far `a` reaches into `b` from the side it touches `b` with.
+fn depth(a: Hitbox, b: Hitbox, side: Side) -> f32 {
+    match side {
+        Side::Bottom => b.y+b.h-(a.y-a.h),
+        Side::Top => a.y+a.h-(b.y-b.h),
+        Side::Left => b.x+b.w-(a.x-a.w),
+        Side::Right => a.x+a.w-(b.x-b.w),
+    }
+}
+
+// Boxes closer than this count as touching, it soaks up float error so
+// something resting on a surface doesn't sink into it or snag on the seam
+// between two tiles.
+const SKIN: f32 = 0.0001;
+
+// Whether the boxes overlap by more than the skin, just touching doesn't count.
+pub fn overlaps(a: Hitbox, b: Hitbox) -> bool {
+    (a.x-b.x).abs() < a.w+b.w-SKIN && (a.y-b.y).abs() < a.h+b.h-SKIN
+}
+
+// Entry and exit time of a move `d` along one axis, as fractions of the move.
+fn axis_times(a: f32, a_half: f32, d: f32, b: f32, b_half: f32) -> (f32, f32, f32) {
+    if d > 0.0 {
+        let gap = (b-b_half)-(a+a_half);
+        (gap/d, ((b+b_half)-(a-a_half))/d, gap)
+    }else if d < 0.0 {
+        let gap = (a-a_half)-(b+b_half);
+        (gap/-d, ((a+a_half)-(b-b_half))/-d, gap)
+    }else if (a-b).abs() < a_half+b_half {
+        (f32::NEG_INFINITY, f32::INFINITY, f32::NEG_INFINITY)
+    }else {
+        (f32::INFINITY, f32::NEG_INFINITY, f32::INFINITY)
+    }
+}
+
+// When `a` moving by (dx, dy) first hits the static box `b`, as a fraction
+// of the move, and the normal of the face it hits. Boxes that already
+// overlap deeper than the skin are left alone.
+pub fn sweep(a: Hitbox, dx: f32, dy: f32, b: Hitbox) -> Option<(f32, (f32, f32))> {
+    let (entry_x, exit_x, gap_x) = axis_times(a.x, a.w, dx, b.x, b.w);
+    let (entry_y, exit_y, gap_y) = axis_times(a.y, a.h, dy, b.y, b.h);
+
+    let entry = entry_x.max(entry_y);
+    let exit = exit_x.min(exit_y);
+    if entry > exit || entry > 1.0 {
+        return None;
+    }
+
+    let time = entry.max(0.0);
+    let (gap, overlap, normal) = if entry_x > entry_y {
+        let y = a.y+dy*time;
+        (gap_x, a.h+b.h-(y-b.y).abs(), (-dx.signum(), 0.0))
+    }else {
+        let x = a.x+dx*time;
+        (gap_y, a.w+b.w-(x-b.x).abs(), (0.0, -dy.signum()))
+    };
+
+    // only faces we are in front of and that we actually run into, not
+    // ones we just graze along
+    if gap < -SKIN || overlap <= SKIN {
+        return None;
+    }
+    Some((time, normal))
+}
+
+fn side(normal: (f32, f32)) -> Side {
+    if normal.1 > 0.0 {
+        Side::Bottom
+    }else if normal.1 < 0.0 {
+        Side::Top
+    }else if normal.0 > 0.0 {
+        Side::Left
+    }else {
+        Side::Right
+    }
+}
+
+// Moves `a` by (dx, dy) through static `solids`. It stops at the first one
+// in the way and slides along it with what's left of the move. Returns the
+// new box and the index of every solid hit.
+pub fn move_and_slide(mut a: Hitbox, mut dx: f32, mut dy: f32, solids: &[Hitbox]) -> (Hitbox, Vec<(usize, Contact)>) {
+    let mut contacts: Vec<(usize, Contact)> = vec![];
+
+    // a corner can stop both axes, a third pass is never needed
+    for _ in 0..2 {
+        if dx == 0.0 && dy == 0.0 {
+            break;
+        }
+
+        let mut first: Option<(f32, (f32, f32), usize)> = None;
+        for (index, solid) in solids.iter().enumerate() {
+            if let Some((time, normal)) = sweep(a, dx, dy, *solid) {
+                if first.is_none() || time < first.unwrap().0 {
+                    first = Some((time, normal, index));
+                }
+            }
+        }
+
+        let (time, normal, index) = match first {
+            Some(first) => first,
+            None => {
+                a.x += dx;
+                a.y += dy;
+                return (a, contacts);
+            },
+        };
+
+        // the overlap the whole move would have ended in
+        let side = side(normal);
+        let depth = depth(Hitbox{x: a.x+dx, y: a.y+dy, ..a}, solids[index], side).max(0.0);
+
+        a.x += dx*time;
+        a.y += dy*time;
+        dx *= 1.0-time;
+        dy *= 1.0-time;
+
+        contacts.push((index, Contact{side, depth, normal}));
+        if normal.0 != 0.0 {
+            dx = 0.0;
+        }else {
+            dy = 0.0;
+        }
+    }
+
+    (a, contacts)
+}
+
+// Static boxes bucketed by the 16x16 cell they cover, so a query only looks
+// at the cells around the area it asks about instead of the whole level.
+pub struct Grid<T> {
+    solids: Vec<(Hitbox, T)>,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+fn cell_range(hitbox: Hitbox) -> ((i32, i32), (i32, i32)) {
+    let col = |x: f32| ((x+1.0)/(2.0*level::TILE_W)).floor() as i32;
+    let row = |y: f32| ((y+1.0)/(2.0*level::TILE_H)).floor() as i32;
+    ((col(hitbox.x-hitbox.w), row(hitbox.y-hitbox.h)), (col(hitbox.x+hitbox.w), row(hitbox.y+hitbox.h)))
+}
+
+impl<T: Copy> Grid<T> {
+    pub fn new() -> Self {
+        Self{solids: vec![], cells: HashMap::new()}
+    }
+
+    pub fn insert(&mut self, hitbox: Hitbox, solid: T) {
+        let index = self.solids.len();
+        self.solids.push((hitbox, solid));
+
+        let ((min_col, min_row), (max_col, max_row)) = cell_range(hitbox);
+        for col in min_col..=max_col {
+            for row in min_row..=max_row {
+                self.cells.entry((col, row)).or_default().push(index);
+            }
+        }
+    }
+
+    // Takes the solid with this hitbox out again, it's never found after.
+    pub fn remove(&mut self, hitbox: Hitbox) {
+        let solids = &self.solids;
+        let ((min_col, min_row), (max_col, max_row)) = cell_range(hitbox);
+        for col in min_col..=max_col {
+            for row in min_row..=max_row {
+                if let Some(indexes) = self.cells.get_mut(&(col, row)) {
+                    indexes.retain(|index| solids[*index].0 != hitbox);
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.solids.len()
+    }
+
+    // Every solid in a cell that `area` touches, each one once.
+    pub fn query(&self, area: Hitbox) -> Vec<(Hitbox, T)> {
+        let mut found: Vec<usize> = vec![];
+        let ((min_col, min_row), (max_col, max_row)) = cell_range(area);
+        for col in min_col..=max_col {
+            for row in min_row..=max_row {
+                if let Some(indexes) = self.cells.get(&(col, row)) {
+                    found.extend(indexes.iter());
+                }
+            }
+        }
+        found.sort();
+        found.dedup();
+        found.into_iter().map(|index| self.solids[index]).collect()
+    }
+}
+
+// The box `a` sweeps through when it moves by (dx, dy).
+pub fn swept_area(a: Hitbox, dx: f32, dy: f32) -> Hitbox {
+    Hitbox{x: a.x+dx/2.0, y: a.y+dy/2.0, h: a.h+dy.abs()/2.0, w: a.w+dx.abs()/2.0}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a tile sized box and a wall a pixel thin to its right
+    const BOX: Hitbox = Hitbox{x: 0.0, y: 0.0, h: 0.0625, w: 0.0625};
+    const WALL: Hitbox = Hitbox{x: 0.5, y: 0.0, h: 0.5, w: 0.004};
+
+    #[test]
+    fn sweep_finds_a_thin_wall_in_a_long_move() {
+        let (time, normal) = sweep(BOX, 4.0, 0.0, WALL).unwrap();
+        assert!(time > 0.0 && time < 0.2);
+        assert!(normal == (-1.0, 0.0));
+    }
+
+    #[test]
+    fn sweep_ignores_walls_behind_and_beside() {
+        assert!(sweep(BOX, -4.0, 0.0, WALL).is_none());
+        assert!(sweep(BOX, 0.0, 4.0, WALL).is_none());
+    }
+
+    #[test]
+    fn move_and_slide_stops_at_a_thin_wall() {
+        let (moved, contacts) = move_and_slide(BOX, 4.0, 0.0, &[WALL]);
+        assert!(moved.x+moved.w <= WALL.x-WALL.w+SKIN);
+        assert!(!overlaps(moved, WALL));
+        assert!(contacts.len() == 1 && contacts[0].1.side == Side::Right);
+    }
+
+    #[test]
+    fn move_and_slide_slides_along_the_floor() {
+        let floor = Hitbox{x: 0.0, y: -0.2, h: 0.004, w: 4.0};
+        let (moved, contacts) = move_and_slide(BOX, 1.0, -3.0, &[floor]);
+        assert!((moved.x-1.0).abs() < 0.001);
+        assert!((moved.y-moved.h-(floor.y+floor.h)).abs() < 0.001);
+        assert!(contacts.len() == 1 && contacts[0].1.side == Side::Bottom);
+    }
+
+    #[test]
+    fn depth_is_the_overlap_along_the_normal() {
+        let below = Hitbox{x: 0.01, y: -0.1, h: 0.0625, w: 0.0625};
+        let contact = contact(BOX, below).unwrap();
+        assert!(contact.side == Side::Bottom && contact.normal == (0.0, 1.0));
+        assert!((contact.depth-0.025).abs() < 0.0001);
+
+        // the box would have ended 0.1 deep in the wall
+        let (_, contacts) = move_and_slide(BOX, WALL.x-WALL.w-BOX.w+0.1, 0.0, &[WALL]);
+        assert!((contacts[0].1.depth-0.1).abs() < 0.0001);
+    }
+}
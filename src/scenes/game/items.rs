@@ -0,0 +1,44 @@
+use crate::scenes::game;
+use crate::scenes::game::collision::{self, Collider, Grid};
+use crate::scenes::game::level;
+use crate::scenes::game::mobs;
+use crate::scenes::game::worlds::Solid;
+
+// items take half a second to rise out of their block
+const RISE_STEP: f32 = level::TILE_H/16.0;
+// mushrooms move a bit faster than goombas
+const ITEM_SPEED: f32 = 0.0008;
+// a star bounces about two tiles high
+const STAR_BOUNCE: f32 = 3.0;
+// how high an item hops when the block under it is bumped
+const BUMP: f32 = 2.5;
+
+// The power ups Mario can take.
+pub fn is_item(name: &str) -> bool {
+    name == "mushroom" || name == "1up" || name == "flower" || name == "star"
+}
+
+// One step of an item. It spawns inside its block and rises out of it,
+// then mushrooms slide along, stars bounce and flowers stay where they are.
+pub fn handle(obj: &mut game::Block, solids: &Grid<Solid>) {
+    let hitbox = obj.hitbox();
+    if solids.query(hitbox).iter().any(|(solid, _)| collision::overlaps(hitbox, *solid)) {
+        obj.y += RISE_STEP;
+        return;
+    }
+
+    match obj.collision_name.as_str() {
+        "mushroom" | "1up" => mobs::walk(obj, solids, ITEM_SPEED),
+        "star" => mobs::hop(obj, solids, ITEM_SPEED, STAR_BOUNCE),
+        _ => {},
+    }
+}
+
+// The block under the item was hit from below at `from_x`, it hops up and
+// away from there.
+pub fn bump(obj: &mut game::Block, from_x: f32) {
+    obj.move_acc_y = BUMP;
+    if obj.move_acc_x != 0.0 {
+        obj.move_acc_x = if obj.x >= from_x {1.0} else {-1.0};
+    }
+}
@@ -0,0 +1,623 @@
+use crate::scenes::game::tilemap;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+
+// Level files are plain text, one entry per line. Positions are tile
+// coordinates: `col` counts 16px columns from the left edge of the level and
+// `row` counts 16px rows up from the bottom of the area. Everything after a
+// `#` is a comment. Files ending in `.map` are ASCII tile maps instead, see
+// tilemap.rs.
+//
+//   name 1-1                      level name shown in error messages
+//   time 400                      level timer
+//   screens 13                    level width in 16 column screens
+//   start <col> <row>             where Mario spawns
+//   checkpoint <col> <row>        where Mario respawns once he got this far
+//   area <name> [<style>]         entries below belong to this area
+//   music <name>                  music of the current area
+//   floor <from_col> <to_col>     two rows of ground
+//   brick <col> <row> [coin | coins <n> | star]
+//   bricks <col> <row> <to_col> <to_row>
+//   question <col> <row> <coin | mushroom | 1up>
+//   stone <col> <row>
+//   stones <col> <row> <to_col> <to_row>
+//   stairs <col> <row> <height> <up | down>
+//   pipe <col> <row> <len> [warp <area> <col> <row>]      row is the top of the pipe
+//   sidepipe <col> <row> <len> [warp <area> <col> <row>]
+//   coin <col> <row>
+//   coins <col> <row> <to_col> <to_row>
+//   goomba <col> <row>
+//   troopa <col> <row>
+//   flag <col> <row>
+//   castle <col> <row> <small | large>   bottom left corner of the castle
+//   lava <from_col> <to_col>      a pit of lava instead of the floor
+//   bridge <col> <row> <to_col>   falls into the lava once the axe is taken
+//   axe <col> <row>               ends a castle level
+//   firebar <col> <row> <len> <cw | ccw>   turns around a block, len fireballs long
+//   bowser <col> <row>            bottom left corner of Bowser
+//   blooper <col> <row>
+//   cheep <col> <row>             a Cheep Cheep
+//
+// A level starts in the `overworld` area and is `screens` wide, other areas
+// (underground, bonus rooms) are as wide as their entries reach. The style
+// of an area, overworld, underground, castle or water, picks its tiles and
+// clear color and defaults to the area name. A castle level restyles its
+// first area with `area overworld castle`, Mario swims in water areas.
+
+pub const TILE_W: f32 = 16.0/256.0;
+pub const TILE_H: f32 = 16.0/240.0;
+pub const SCREEN_COLS: i32 = 16;
+
+pub const STYLES: [&str; 4] = ["overworld", "underground", "castle", "water"];
+
+const ENTRIES: [&str; 22] = [
+    "floor", "brick", "bricks", "question", "stone", "stones", "stairs", "pipe",
+    "sidepipe", "coin", "coins", "goomba", "troopa", "flag", "castle", "lava",
+    "bridge", "axe", "firebar", "bowser", "blooper", "cheep",
+];
+
+pub fn tile_x(col: i32) -> f32 {
+    -1.0+TILE_W*(2*col+1) as f32
+}
+
+pub fn tile_y(row: i32) -> f32 {
+    -1.0+TILE_H*(2*row+1) as f32
+}
+
+#[derive(Clone)]
+pub struct Area {
+    pub name: String,
+    pub style: String,
+    pub music: String,
+}
+
+impl Area {
+    fn create(name: &str, style: &str) -> Self {
+        Self{name: name.to_string(), style: style.to_string(), music: style.to_string()}
+    }
+}
+
+#[derive(Clone)]
+pub struct Warp {
+    pub area: String,
+    pub col: i32,
+    pub row: i32,
+}
+
+#[derive(Clone)]
+pub enum Item {
+    Floor{to_col: i32},
+    Bricks{to_col: i32, to_row: i32, content: String, count: u32},
+    Question{content: String},
+    Stones{to_col: i32, to_row: i32},
+    Stairs{height: i32, up: bool},
+    Pipe{len: usize, warp: Option<Warp>},
+    SidePipe{len: usize, warp: Option<Warp>},
+    Coins{to_col: i32, to_row: i32},
+    Goomba,
+    Troopa,
+    Flag,
+    Castle{size: String},
+    Lava{to_col: i32},
+    Bridge{to_col: i32},
+    Axe,
+    Firebar{len: usize, clockwise: bool},
+    Bowser,
+    Blooper,
+    Cheep,
+}
+
+#[derive(Clone)]
+pub struct Entry {
+    pub line: usize,
+    pub area: String,
+    pub col: i32,
+    pub row: i32,
+    pub item: Item,
+}
+
+impl Entry {
+    pub fn name(&self) -> &str {
+        match self.item {
+            Item::Floor{..} => "floor",
+            Item::Bricks{..} => "brick",
+            Item::Question{..} => "question",
+            Item::Stones{..} => "stone",
+            Item::Stairs{..} => "stairs",
+            Item::Pipe{..} => "pipe",
+            Item::SidePipe{..} => "sidepipe",
+            Item::Coins{..} => "coin",
+            Item::Goomba => "goomba",
+            Item::Troopa => "troopa",
+            Item::Flag => "flag",
+            Item::Castle{..} => "castle",
+            Item::Lava{..} => "lava",
+            Item::Bridge{..} => "bridge",
+            Item::Axe => "axe",
+            Item::Firebar{..} => "firebar",
+            Item::Bowser => "bowser",
+            Item::Blooper => "blooper",
+            Item::Cheep => "cheep",
+        }
+    }
+
+    // every tile the entry fills, used to find overlapping entries
+    pub fn cells(&self) -> Vec<(i32, i32)> {
+        let mut cells: Vec<(i32, i32)> = vec![];
+        let (col, row) = (self.col, self.row);
+        match &self.item {
+            Item::Floor{to_col} | Item::Lava{to_col} => {
+                for c in col..=*to_col {
+                    cells.push((c, 0));
+                    cells.push((c, 1));
+                }
+            },
+            Item::Bridge{to_col} => {
+                for c in col..=*to_col {
+                    cells.push((c, row));
+                }
+            },
+            Item::Bowser => {
+                for c in col..col+2 {
+                    cells.push((c, row));
+                    cells.push((c, row+1));
+                }
+            },
+            Item::Bricks{to_col, to_row, ..} | Item::Stones{to_col, to_row} | Item::Coins{to_col, to_row} => {
+                for c in col..=*to_col {
+                    for r in row..=*to_row {
+                        cells.push((c, r));
+                    }
+                }
+            },
+            Item::Stairs{height, up} => {
+                for i in 0..*height {
+                    let column_height = if *up {i+1} else {height-i};
+                    for r in row..row+column_height {
+                        cells.push((col+i, r));
+                    }
+                }
+            },
+            Item::Pipe{len, ..} => {
+                for r in row-*len as i32..=row {
+                    cells.push((col, r));
+                    cells.push((col+1, r));
+                }
+            },
+            Item::SidePipe{len, ..} => {
+                for c in col..col+4 {
+                    cells.push((c, row));
+                    cells.push((c, row+1));
+                }
+                for r in row+2..row+2+*len as i32 {
+                    cells.push((col+2, r));
+                    cells.push((col+3, r));
+                }
+            },
+            Item::Flag => {
+                for r in row..=row+10 {
+                    cells.push((col, r));
+                }
+            },
+            // the castle is only a backdrop
+            Item::Castle{..} => {},
+            Item::Question{..} | Item::Goomba | Item::Troopa | Item::Axe | Item::Firebar{..} | Item::Blooper | Item::Cheep => cells.push((col, row)),
+        }
+        cells
+    }
+}
+
+pub struct LevelError {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            return write!(f, "{}: {}", self.file, self.message);
+        }
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+#[derive(Clone)]
+pub struct Level {
+    pub name: String,
+    pub time: u32,
+    pub screens: i32,
+    pub start: (i32, i32),
+    pub checkpoint: Option<(i32, i32)>,
+    pub areas: Vec<Area>,
+    pub entries: Vec<Entry>,
+}
+
+pub fn number<T: std::str::FromStr>(token: &str) -> Result<T, String> {
+    token.parse::<T>().map_err(|_| format!("expected a number, found `{}`", token))
+}
+
+pub fn parse_warp(tokens: &[&str]) -> Result<Option<Warp>, String> {
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+    if tokens.len() != 4 || tokens[0] != "warp" {
+        return Err("expected `warp <area> <col> <row>`".to_string());
+    }
+    Ok(Some(Warp{area: tokens[1].to_string(), col: number(tokens[2])?, row: number(tokens[3])?}))
+}
+
+pub fn parse_turn(token: &str) -> Result<bool, String> {
+    match token {
+        "cw" => Ok(true),
+        "ccw" => Ok(false),
+        _ => Err(format!("a firebar turns `cw` or `ccw`, not `{}`", token)),
+    }
+}
+
+fn parse_item(keyword: &str, args: &[&str]) -> Result<(i32, i32, Item), String> {
+    let expect = |count: usize, usage: &str| -> Result<(), String> {
+        if args.len() != count {
+            return Err(format!("`{}` expects {}", keyword, usage));
+        }
+        Ok(())
+    };
+
+    if !ENTRIES.contains(&keyword) {
+        return Err(format!("unknown entry `{}`", keyword));
+    }
+
+    if keyword == "floor" || keyword == "lava" {
+        expect(2, "<from_col> <to_col>")?;
+        let to_col = number(args[1])?;
+        let item = if keyword == "floor" {Item::Floor{to_col}} else {Item::Lava{to_col}};
+        return Ok((number(args[0])?, 0, item));
+    }
+
+    if args.len() < 2 {
+        return Err(format!("`{}` expects at least <col> <row>", keyword));
+    }
+    let col = number(args[0])?;
+    let row = number(args[1])?;
+
+    let item = match keyword {
+        "brick" => {
+            let (content, count) = match &args[2..] {
+                [] => ("".to_string(), 0),
+                ["coin"] => ("block".to_string(), 1),
+                ["coins", n] => ("block".to_string(), number(n)?),
+                ["star"] => ("star".to_string(), 1),
+                other => return Err(format!("unknown brick content `{}`", other.join(" "))),
+            };
+            Item::Bricks{to_col: col, to_row: row, content, count}
+        },
+        "bricks" => {
+            expect(4, "<col> <row> <to_col> <to_row>")?;
+            Item::Bricks{to_col: number(args[2])?, to_row: number(args[3])?, content: "".to_string(), count: 0}
+        },
+        "question" => {
+            expect(3, "<col> <row> <coin | mushroom | 1up>")?;
+            if args[2] != "coin" && args[2] != "mushroom" && args[2] != "1up" {
+                return Err(format!("unknown question block content `{}`", args[2]));
+            }
+            Item::Question{content: args[2].to_string()}
+        },
+        "stone" => {
+            expect(2, "<col> <row>")?;
+            Item::Stones{to_col: col, to_row: row}
+        },
+        "stones" => {
+            expect(4, "<col> <row> <to_col> <to_row>")?;
+            Item::Stones{to_col: number(args[2])?, to_row: number(args[3])?}
+        },
+        "stairs" => {
+            expect(4, "<col> <row> <height> <up | down>")?;
+            if args[3] != "up" && args[3] != "down" {
+                return Err(format!("stairs go `up` or `down`, not `{}`", args[3]));
+            }
+            Item::Stairs{height: number(args[2])?, up: args[3] == "up"}
+        },
+        "pipe" | "sidepipe" => {
+            if args.len() < 3 {
+                return Err(format!("`{}` expects <col> <row> <len> [warp <area> <col> <row>]", keyword));
+            }
+            let len = number(args[2])?;
+            let warp = parse_warp(&args[3..])?;
+            if keyword == "pipe" {
+                Item::Pipe{len, warp}
+            }else {
+                Item::SidePipe{len, warp}
+            }
+        },
+        "coin" => {
+            expect(2, "<col> <row>")?;
+            Item::Coins{to_col: col, to_row: row}
+        },
+        "coins" => {
+            expect(4, "<col> <row> <to_col> <to_row>")?;
+            Item::Coins{to_col: number(args[2])?, to_row: number(args[3])?}
+        },
+        "goomba" => {
+            expect(2, "<col> <row>")?;
+            Item::Goomba
+        },
+        "troopa" => {
+            expect(2, "<col> <row>")?;
+            Item::Troopa
+        },
+        "flag" => {
+            expect(2, "<col> <row>")?;
+            Item::Flag
+        },
+        "castle" => {
+            expect(3, "<col> <row> <small | large>")?;
+            if args[2] != "small" && args[2] != "large" {
+                return Err(format!("unknown castle size `{}`", args[2]));
+            }
+            Item::Castle{size: args[2].to_string()}
+        },
+        "bridge" => {
+            expect(3, "<col> <row> <to_col>")?;
+            Item::Bridge{to_col: number(args[2])?}
+        },
+        "axe" => {
+            expect(2, "<col> <row>")?;
+            Item::Axe
+        },
+        "firebar" => {
+            expect(4, "<col> <row> <len> <cw | ccw>")?;
+            Item::Firebar{len: number(args[2])?, clockwise: parse_turn(args[3])?}
+        },
+        "bowser" => {
+            expect(2, "<col> <row>")?;
+            Item::Bowser
+        },
+        "blooper" => {
+            expect(2, "<col> <row>")?;
+            Item::Blooper
+        },
+        "cheep" => {
+            expect(2, "<col> <row>")?;
+            Item::Cheep
+        },
+        _ => unreachable!(),
+    };
+
+    Ok((col, row, item))
+}
+
+pub fn parse_header(keyword: &str, args: &[&str], level: &mut Level, area: &mut String) -> Result<(), String> {
+    if keyword == "start" || keyword == "checkpoint" {
+        if args.len() != 2 {
+            return Err(format!("`{}` expects <col> <row>", keyword));
+        }
+        let spawn = (number(args[0])?, number(args[1])?);
+        if keyword == "start" {
+            level.start = spawn;
+        }else {
+            level.checkpoint = Some(spawn);
+        }
+        return Ok(());
+    }
+
+    if keyword == "area" {
+        if args.is_empty() || args.len() > 2 {
+            return Err("`area` expects <name> [<style>]".to_string());
+        }
+        let style = args.get(1).copied().or(STYLES.iter().copied().find(|style| *style == args[0]));
+        if let Some(style) = style {
+            if !STYLES.contains(&style) {
+                return Err(format!("unknown style `{}`", style));
+            }
+        }
+        match (level.areas.iter_mut().find(|other| other.name == args[0]), style) {
+            // the music goes with the style unless it was set
+            (Some(other), Some(style)) => {
+                if other.music == other.style {
+                    other.music = style.to_string();
+                }
+                other.style = style.to_string();
+            },
+            (Some(_), None) => {},
+            (None, Some(style)) => level.areas.push(Area::create(args[0], style)),
+            (None, None) => return Err(format!("area `{}` needs a style", args[0])),
+        }
+        *area = args[0].to_string();
+        return Ok(());
+    }
+
+    if args.len() != 1 {
+        return Err(format!("`{}` expects one value", keyword));
+    }
+    match keyword {
+        "name" => level.name = args[0].to_string(),
+        "time" => level.time = number(args[0])?,
+        "screens" => level.screens = number(args[0])?,
+        _ => level.area_mut(area).music = args[0].to_string(),
+    }
+    Ok(())
+}
+
+impl Level {
+    pub fn new(file: &str) -> Self {
+        let areas = vec![Area::create("overworld", "overworld")];
+        Level{name: file.to_string(), time: 400, screens: 0, start: (0, 0), checkpoint: None, areas, entries: vec![]}
+    }
+
+    pub fn load(path: &str) -> Result<Self, Vec<LevelError>> {
+        match fs::read_to_string(path) {
+            Ok(src) if path.ends_with(".map") => tilemap::parse(path, &src),
+            Ok(src) => Level::parse(path, &src),
+            Err(err) => Err(vec![LevelError{file: path.to_string(), line: 0, message: err.to_string()}]),
+        }
+    }
+
+    pub fn parse(file: &str, src: &str) -> Result<Self, Vec<LevelError>> {
+        let mut errors: Vec<LevelError> = vec![];
+        let mut error = |line: usize, message: String| {
+            errors.push(LevelError{file: file.to_string(), line, message});
+        };
+
+        let mut level = Level::new(file);
+        let mut area = "overworld".to_string();
+
+        for (index, text) in src.lines().enumerate() {
+            let line = index+1;
+            let text = text.split('#').next().unwrap();
+            let tokens: Vec<&str> = text.split_whitespace().collect();
+            if tokens.is_empty() {
+                continue;
+            }
+            let (keyword, args) = (tokens[0], &tokens[1..]);
+
+            let result = match keyword {
+                "name" | "time" | "screens" | "start" | "checkpoint" | "area" | "music" => parse_header(keyword, args, &mut level, &mut area),
+                _ => parse_item(keyword, args).map(|(col, row, item)| {
+                    level.entries.push(Entry{line, area: area.clone(), col, row, item});
+                }),
+            };
+
+            if let Err(message) = result {
+                error(line, message);
+            }
+        }
+
+        for (line, message) in level.check() {
+            error(line, message);
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(level)
+    }
+
+    // problems that don't depend on the file format, as (line, message)
+    pub fn check(&self) -> Vec<(usize, String)> {
+        let mut errors: Vec<(usize, String)> = vec![];
+
+        if self.screens <= 0 {
+            errors.push((0, "missing `screens`".to_string()));
+        }
+
+        // overlapping and out of bounds entries, only the first area has a
+        // set width
+        let entries = &self.entries;
+        let mut taken: HashMap<(String, i32, i32), usize> = HashMap::new();
+        for (index, entry) in entries.iter().enumerate() {
+            let width = if entry.area == self.areas[0].name {self.screens*SCREEN_COLS} else {i32::MAX};
+            for (col, row) in entry.cells() {
+                if col < 0 || col >= width || row < 0 {
+                    errors.push((entry.line, format!("`{}` at {} {} is outside the level", entry.name(), col, row)));
+                    break;
+                }
+                if let Some(other) = taken.insert((entry.area.clone(), col, row), index) {
+                    let other = &entries[other];
+                    errors.push((entry.line, format!("`{}` overlaps `{}` from line {} at {} {}", entry.name(), other.name(), other.line, col, row)));
+                    break;
+                }
+            }
+            if let Item::Pipe{warp: Some(warp), ..} | Item::SidePipe{warp: Some(warp), ..} = &entry.item {
+                if !self.areas.iter().any(|area| area.name == warp.area) {
+                    errors.push((entry.line, format!("unknown area `{}`", warp.area)));
+                }
+            }
+        }
+        errors
+    }
+
+    pub fn area(&self, name: &str) -> &Area {
+        self.areas.iter().find(|area| area.name == name).unwrap()
+    }
+
+    fn area_mut(&mut self, name: &str) -> &mut Area {
+        self.areas.iter_mut().find(|area| area.name == name).unwrap()
+    }
+
+    // How many screens wide an area is, the camera doesn't scroll past it.
+    pub fn screens_in(&self, area: &str) -> i32 {
+        if area == self.areas[0].name {
+            return self.screens;
+        }
+        self.entries_in(area).flat_map(|entry| entry.cells()).map(|(col, _)| col/SCREEN_COLS+1).max().unwrap_or(1)
+    }
+
+    pub fn entries_in<'a>(&'a self, area: &'a str) -> impl Iterator<Item = &'a Entry> + 'a {
+        self.entries.iter().filter(move |entry| entry.area == area)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every error of a level as it's printed, file:line: message
+    fn errors(src: &str) -> Vec<String> {
+        match Level::parse("test.lvl", src) {
+            Ok(_) => vec![],
+            Err(errors) => errors.iter().map(|error| error.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn a_good_level_has_no_errors() {
+        assert!(errors("screens 1\nstart 1 2\nfloor 0 15\narea cellar underground\nstone 40 2\n").is_empty());
+    }
+
+    #[test]
+    fn a_missing_width_has_no_line() {
+        assert_eq!(errors("start 1 2\n"), vec!["test.lvl: missing `screens`"]);
+    }
+
+    #[test]
+    fn bad_lines_point_at_their_line() {
+        let src = "screens 1\nbrick 1\nwall 2 2\nquestion 3 3 gold\ntime soon\narea cellar\nstairs 1 2 3 sideways\n";
+        assert_eq!(errors(src), vec![
+            "test.lvl:2: `brick` expects at least <col> <row>",
+            "test.lvl:3: unknown entry `wall`",
+            "test.lvl:4: unknown question block content `gold`",
+            "test.lvl:5: expected a number, found `soon`",
+            "test.lvl:6: area `cellar` needs a style",
+            "test.lvl:7: stairs go `up` or `down`, not `sideways`",
+        ]);
+    }
+
+    #[test]
+    fn entries_outside_the_level() {
+        assert_eq!(errors("screens 1\nstone 16 2\nstone 3 -1\n"), vec![
+            "test.lvl:2: `stone` at 16 2 is outside the level",
+            "test.lvl:3: `stone` at 3 -1 is outside the level",
+        ]);
+    }
+
+    #[test]
+    fn overlapping_entries() {
+        assert_eq!(errors("screens 1\nstone 4 2\nbricks 3 2 5 2\n"), vec![
+            "test.lvl:3: `brick` overlaps `stone` from line 2 at 4 2",
+        ]);
+    }
+
+    #[test]
+    fn other_areas_only_overlap_themselves() {
+        assert!(errors("screens 1\nstone 4 2\narea cellar underground\nstone 4 2\n").is_empty());
+    }
+
+    #[test]
+    fn warps_go_to_known_areas() {
+        assert_eq!(errors("screens 1\npipe 4 5 1 warp cellar 1 1\n"), vec![
+            "test.lvl:2: unknown area `cellar`",
+        ]);
+    }
+
+    #[test]
+    fn a_missing_file_is_an_error_without_a_line() {
+        let errors = match Level::load("no/such/level.lvl") {
+            Ok(_) => vec![],
+            Err(errors) => errors.iter().map(|error| error.to_string()).collect(),
+        };
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("no/such/level.lvl: "));
+    }
+}
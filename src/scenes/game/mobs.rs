@@ -1,8 +1,81 @@
-use crate::render;
 use crate::scenes::game;
-use crate::scenes::game::objects;
+use crate::scenes::game::collision::{self, Collider, Grid, Hitbox, Side};
+use crate::scenes::game::level;
+use crate::scenes::game::projectile::Projectile;
+use crate::scenes::game::simulation::{Rng, STEP_MS, WATER_TOP_ROW};
+use crate::scenes::game::worlds::Solid;
 use std::path::Path;
-use std::ffi::{CString, c_void};
+
+// mobs walk at half of Mario's speed and swap walking frames every few steps
+const WALK_SPEED: f32 = 0.0005;
+const WALK_FRAME_STEPS: usize = 10;
+// how long a squashed goomba lies around before it's gone
+const SQUASH_STEPS: usize = 30;
+// a kicked shell slides faster than Mario runs
+const SHELL_SPEED: f32 = 0.002;
+// a kicked shell can't hurt Mario until it had time to get away from him
+const KICK_STEPS: usize = 10;
+// a shell left alone for five seconds comes back out
+const SHELL_WAKE_STEPS: usize = 300;
+// points for every mob a sliding shell knocks out in a row
+const SHELL_SCORES: [u32; 7] = [500, 800, 1000, 2000, 4000, 5000, 8000];
+// Bowser paces two tiles either way of where he stands, slower than the
+// other mobs walk
+const BOWSER_SPEED: f32 = 0.0003;
+const BOWSER_PACE: f32 = 4.0*level::TILE_W;
+const BOWSER_JUMP: f32 = 2.5;
+// steps between his jumps and between his flames, at least the first number
+// plus up to the second one
+const BOWSER_JUMP_STEPS: (u32, u32) = (90, 120);
+const BOWSER_FIRE_STEPS: (u32, u32) = (100, 100);
+// he opens his mouth this long before the flame comes out
+const BOWSER_MOUTH_STEPS: u32 = 30;
+// Mario's fireballs knock him out after this many hits
+const BOWSER_HITS: u32 = 5;
+// a blooper sinks slowly, then pushes itself up in a burst that slows down
+const BLOOPER_SINK: f32 = 0.0004;
+const BLOOPER_PUSH: f32 = 0.0015;
+const BLOOPER_PUSH_STEPS: u32 = 30;
+// Cheep Cheeps swim slower than Mario walks and bob up and down a little
+const CHEEP_SPEED: f32 = 0.0003;
+const CHEEP_BOB: f32 = 0.0001;
+const CHEEP_BOB_STEPS: u32 = 60;
+
+// One step of walking and falling. `move_acc_x` is the walking direction,
+// walls and pipes turn the mob around.
+pub fn walk(obj: &mut game::Block, solids: &Grid<Solid>, speed: f32) {
+    hop(obj, solids, speed, 0.0);
+}
+
+// Walking that jumps off the floor again `bounce` high.
+pub fn hop(obj: &mut game::Block, solids: &Grid<Solid>, speed: f32, bounce: f32) {
+    obj.move_acc_y -= 0.15;
+    let move_x = STEP_MS*speed*obj.move_acc_x;
+    let move_y = STEP_MS*0.001*obj.move_acc_y;
+
+    let hitbox = obj.hitbox();
+    let nearby: Vec<Hitbox> = solids.query(collision::swept_area(hitbox, move_x, move_y)).iter().map(|(solid, _)| *solid).collect();
+    let (hitbox, contacts) = collision::move_and_slide(hitbox, move_x, move_y, &nearby);
+    obj.x = hitbox.x;
+    obj.y = hitbox.y;
+
+    for (_, contact) in contacts {
+        match contact.side {
+            Side::Bottom => obj.move_acc_y = bounce,
+            Side::Top => obj.move_acc_y = 0.0,
+            Side::Left | Side::Right => obj.move_acc_x = contact.normal.0,
+        }
+    }
+}
+
+// Flips between the first two sprites while walking.
+fn animate(delay: &mut usize, state: &mut usize) {
+    *delay += 1;
+    if *delay == WALK_FRAME_STEPS {
+        *delay = 0;
+        *state = 1-*state;
+    }
+}
 
 
 pub struct Troopa {
@@ -12,27 +85,17 @@ pub struct Troopa {
     pub to_move: bool,
     pub is_squash: bool,
     pub to_move_squash: bool,
-    pub program: render::Program,
+    pub kills: usize,
 }
 
 impl Troopa {
+    // x and y are the center of the tile it stands on, it's taller than that
     pub fn create(x: f32, y: f32) -> Self {
-        let mut obj = game::Block::create(0.0, 0.0, 24.0/240.0, 16.0/256.0, false, &Path::new("src/scenes/game/assets/images/troopa1.png"), "troopa");
-
-        let vert_shader = render::Shader::vertex_from_src(
-            &CString::new(include_str!("assets/shaders/troopa.vert")).unwrap(),
-        ).unwrap();
-        
-        let frag_shader = render::Shader::fragment_from_src(
-            &CString::new(include_str!("assets/shaders/troopa.frag")).unwrap(),
-        ).unwrap();
-        let program = render::Program::create_with_shaders(&[vert_shader, frag_shader]).unwrap();
-
-        obj.textures.push(render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/troopa2.png")));
-        obj.textures.push(render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/troopa_squash.png")));
-        
-        obj.x = x;
-        obj.y = y;
+        let y = y+8.0/240.0;
+        let mut obj = game::Block::create(x, y, 24.0/240.0, 16.0/256.0, false, Path::new("src/scenes/game/assets/images/troopa1.png"), "troopa");
+
+        obj.add_sprite(Path::new("src/scenes/game/assets/images/troopa2.png"));
+        obj.add_sprite(Path::new("src/scenes/game/assets/images/troopa_squash.png"));
 
         obj.move_acc_x = -1.0;
 
@@ -41,24 +104,76 @@ impl Troopa {
         let to_move = false;
         let is_squash = false;
         let to_move_squash = false;
+        let kills = 0;
 
-        Self{delay, state, obj, to_move, is_squash, to_move_squash, program}
+        Self{delay, state, obj, to_move, is_squash, to_move_squash, kills}
     }
 
     pub fn squash(&mut self) {
         let x = self.obj.x;
         let y = self.obj.y;
-        self.obj = game::Block::create(0.0, 0.0, 16.0/240.0, 16.0/256.0, false, &Path::new("src/scenes/game/assets/images/troopa_squash.png"), "goomba");
-        self.obj.x = x;
-        self.obj.y = y;
+        self.obj = game::Block::create(x, y-8.0/240.0, 16.0/240.0, 16.0/256.0, false, Path::new("src/scenes/game/assets/images/troopa_squash.png"), "troopa");
 
+        self.state = 0;
+        self.delay = 0;
         self.is_squash = true;
     } 
 
-    pub unsafe fn draw(&self) {
-        gl::BindTexture(gl::TEXTURE_2D, self.obj.textures[self.state].texture);
-        gl::BindVertexArray(self.obj.obj.vao);
-        gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, std::ptr::null());
+    // Sends the shell sliding in `dir`.
+    pub fn kick(&mut self, dir: f32) {
+        self.obj.move_acc_x = dir;
+        self.delay = 0;
+        self.kills = 0;
+        self.to_move_squash = true;
+    }
+
+    pub fn stop(&mut self) {
+        self.obj.move_acc_x = 0.0;
+        self.delay = 0;
+        self.to_move_squash = false;
+    }
+
+    // A squashed troopa hides in its shell, `delay` counts how long it's
+    // been still or sliding. Left alone it comes back out walking.
+    pub fn handle(&mut self, solids: &Grid<Solid>) {
+        if !self.is_squash {
+            walk(&mut self.obj, solids, WALK_SPEED);
+            animate(&mut self.delay, &mut self.state);
+            return;
+        }
+
+        walk(&mut self.obj, solids, SHELL_SPEED);
+        self.delay += 1;
+        if !self.to_move_squash && self.delay == SHELL_WAKE_STEPS {
+            *self = Troopa::create(self.obj.x, self.obj.y);
+            self.to_move = true;
+        }
+    }
+
+    pub fn is_harmful(&self) -> bool {
+        !self.is_squash || (self.to_move_squash && self.delay >= KICK_STEPS)
+    }
+
+    // The points for the next mob this shell knocks out.
+    pub fn knock_out(&mut self) -> u32 {
+        let score = SHELL_SCORES[self.kills.min(SHELL_SCORES.len()-1)];
+        self.kills += 1;
+        score
+    }
+
+    pub fn sprite(&self) -> &str {
+        &self.obj.sprites[self.state]
+    }
+
+    // the sprite looks left
+    pub fn flip(&self) -> bool {
+        self.obj.move_acc_x > 0.0
+    }
+}
+
+impl Collider for Troopa {
+    fn hitbox(&self) -> Hitbox {
+        self.obj.hitbox()
     }
 }
 
@@ -68,27 +183,14 @@ pub struct Goomba {
     pub obj: game::Block,
     pub to_move: bool,
     pub is_squash: bool,
-    pub program: render::Program,
 }
 
 impl Goomba {
     pub fn create(x: f32, y: f32) -> Self {
-        let mut obj = game::Block::create(0.0, 0.0, 16.0/240.0, 16.0/256.0, false, &Path::new("src/scenes/game/assets/images/goomba1.png"), "goomba");
-
-        let vert_shader = render::Shader::vertex_from_src(
-            &CString::new(include_str!("assets/shaders/goomba.vert")).unwrap(),
-        ).unwrap();
-        
-        let frag_shader = render::Shader::fragment_from_src(
-            &CString::new(include_str!("assets/shaders/goomba.frag")).unwrap(),
-        ).unwrap();
-        let program = render::Program::create_with_shaders(&[vert_shader, frag_shader]).unwrap();
-
-        obj.textures.push(render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/goomba2.png")));
-        obj.textures.push(render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/goomba_squash.png")));
-        
-        obj.x = x;
-        obj.y = y;
+        let mut obj = game::Block::create(x, y, 16.0/240.0, 16.0/256.0, false, Path::new("src/scenes/game/assets/images/goomba1.png"), "goomba");
+
+        obj.add_sprite(Path::new("src/scenes/game/assets/images/goomba2.png"));
+        obj.add_sprite(Path::new("src/scenes/game/assets/images/goomba_squash.png"));
 
         obj.move_acc_x = -1.0;
 
@@ -97,24 +199,237 @@ impl Goomba {
         let to_move = false;
         let is_squash = false;
 
-        Self{delay, state, obj, to_move, is_squash, program}
+        Self{delay, state, obj, to_move, is_squash}
     }
 
     pub fn squash(&mut self) {
         let x = self.obj.x;
         let y = self.obj.y;
-        self.obj = game::Block::create(0.0, 0.0, 8.0/240.0, 16.0/256.0, false, &Path::new("src/scenes/game/assets/images/goomba_squash.png"), "goomba");
-        self.obj.x = x;
-        self.obj.y = y;
+        self.obj = game::Block::create(x, y-8.0/240.0, 8.0/240.0, 16.0/256.0, false, Path::new("src/scenes/game/assets/images/goomba_squash.png"), "goomba");
 
         self.state = 0;
+        self.delay = 0;
 
         self.is_squash = true;
     } 
 
-    pub unsafe fn draw(&self) {
-        gl::BindTexture(gl::TEXTURE_2D, self.obj.textures[self.state].texture);
-        gl::BindVertexArray(self.obj.obj.vao);
-        gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, std::ptr::null());
+    // a squashed goomba stays flat where it was stomped, `delay` counts how
+    // long it's been lying there
+    pub fn handle(&mut self, solids: &Grid<Solid>) {
+        if self.is_squash {
+            self.delay += 1;
+            return;
+        }
+        walk(&mut self.obj, solids, WALK_SPEED);
+        animate(&mut self.delay, &mut self.state);
+    }
+
+    pub fn is_gone(&self) -> bool {
+        self.is_squash && self.delay >= SQUASH_STEPS
+    }
+
+    pub fn sprite(&self) -> &str {
+        &self.obj.sprites[self.state]
+    }
+}
+
+impl Collider for Goomba {
+    fn hitbox(&self) -> Hitbox {
+        self.obj.hitbox()
+    }
+}
+
+pub struct Bowser {
+    pub obj: game::Block,
+    pub to_move: bool,
+    // he turns to face Mario, the sprite looks left
+    pub flip: bool,
+    // where he stands in the level, he paces around it
+    home_x: f32,
+    jump_delay: u32,
+    fire_delay: u32,
+    hits: u32,
+}
+
+impl Bowser {
+    // x and y are the center of his bottom left tile, he's two tiles wide
+    // and two high
+    pub fn create(x: f32, y: f32) -> Self {
+        let mut obj = game::Block::create(x+level::TILE_W, y+level::TILE_H, 2.0*level::TILE_H, 2.0*level::TILE_W, false, Path::new("src/scenes/game/assets/images/bowser1.png"), "bowser");
+        obj.add_sprite(Path::new("src/scenes/game/assets/images/bowser2.png"));
+
+        obj.move_acc_x = -1.0;
+
+        let home_x = obj.x;
+        let to_move = false;
+        let flip = false;
+        let jump_delay = BOWSER_JUMP_STEPS.0;
+        let fire_delay = BOWSER_FIRE_STEPS.0;
+        let hits = 0;
+
+        Self{obj, to_move, flip, home_x, jump_delay, fire_delay, hits}
+    }
+
+    fn wait(rng: &mut Rng, steps: (u32, u32)) -> u32 {
+        steps.0+rng.next()%steps.1
+    }
+
+    // One step of pacing, jumping and breathing fire. Returns the flame when
+    // he breathes one at Mario.
+    pub fn handle(&mut self, solids: &Grid<Solid>, rng: &mut Rng, mario_x: f32) -> Option<Projectile> {
+        self.flip = mario_x > self.obj.x;
+        if self.obj.x < self.home_x-BOWSER_PACE {
+            self.obj.move_acc_x = 1.0;
+        }else if self.obj.x > self.home_x+BOWSER_PACE {
+            self.obj.move_acc_x = -1.0;
+        }
+        walk(&mut self.obj, solids, BOWSER_SPEED);
+
+        // he only jumps off the ground
+        self.jump_delay -= 1;
+        if self.jump_delay == 0 {
+            self.jump_delay = Self::wait(rng, BOWSER_JUMP_STEPS);
+            if self.obj.move_acc_y == 0.0 {
+                self.obj.move_acc_y = BOWSER_JUMP;
+            }
+        }
+
+        // he only breathes fire standing, low enough to reach small Mario
+        self.fire_delay = self.fire_delay.saturating_sub(1);
+        self.obj.state = (self.fire_delay <= BOWSER_MOUTH_STEPS) as usize;
+        if self.fire_delay > 0 || self.obj.move_acc_y != 0.0 {
+            return None;
+        }
+        self.fire_delay = Self::wait(rng, BOWSER_FIRE_STEPS)+BOWSER_MOUTH_STEPS;
+        let dir = if self.flip {1.0} else {-1.0};
+        Some(Projectile::flame(self.obj.x+dir*self.obj.w, self.obj.y, dir))
+    }
+
+    // With the bridge gone he falls into the lava, through everything.
+    pub fn fall(&mut self) {
+        self.obj.state = 0;
+        self.obj.move_acc_y -= 0.15;
+        self.obj.y += STEP_MS*0.001*self.obj.move_acc_y;
+    }
+
+    // Returns true once Mario's fireballs hit him often enough.
+    pub fn hit(&mut self) -> bool {
+        self.hits += 1;
+        self.hits == BOWSER_HITS
+    }
+
+    pub fn sprite(&self) -> &str {
+        self.obj.sprite()
+    }
+}
+
+impl Collider for Bowser {
+    fn hitbox(&self) -> Hitbox {
+        self.obj.hitbox()
+    }
+}
+
+// Swimming mobs go through everything but stay in the water, above the sea
+// floor and below the surface.
+fn keep_in_water(obj: &mut game::Block) {
+    let floor = level::tile_y(2)-level::TILE_H;
+    let top = level::tile_y(WATER_TOP_ROW)+level::TILE_H;
+    obj.y = obj.y.clamp(floor+obj.h, top-obj.h);
+}
+
+pub struct Blooper {
+    pub obj: game::Block,
+    pub to_move: bool,
+    // steps left of its push, it sinks in between
+    push_delay: u32,
+}
+
+impl Blooper {
+    pub fn create(x: f32, y: f32) -> Self {
+        let mut obj = game::Block::create(x, y+8.0/240.0, 24.0/240.0, 16.0/256.0, false, Path::new("src/scenes/game/assets/images/blooper1.png"), "blooper");
+        obj.add_sprite(Path::new("src/scenes/game/assets/images/blooper2.png"));
+
+        let to_move = false;
+        let push_delay = 0;
+
+        Self{obj, to_move, push_delay}
+    }
+
+    // It sinks until it's as low as Mario or on the sea floor, then pushes
+    // off up and toward him.
+    pub fn handle(&mut self, mario: Hitbox) {
+        if self.push_delay > 0 {
+            let speed = STEP_MS*BLOOPER_PUSH*self.push_delay as f32/BLOOPER_PUSH_STEPS as f32;
+            self.obj.x += self.obj.move_acc_x*speed;
+            self.obj.y += speed;
+            self.push_delay -= 1;
+        }else {
+            let y = self.obj.y;
+            self.obj.y -= STEP_MS*BLOOPER_SINK;
+            keep_in_water(&mut self.obj);
+            if self.obj.y-self.obj.h <= mario.y-mario.h || self.obj.y == y {
+                self.push_delay = BLOOPER_PUSH_STEPS;
+                self.obj.move_acc_x = if mario.x > self.obj.x {1.0} else {-1.0};
+            }
+        }
+        keep_in_water(&mut self.obj);
+        self.obj.state = (self.push_delay == 0) as usize;
+    }
+
+    pub fn sprite(&self) -> &str {
+        self.obj.sprite()
+    }
+}
+
+impl Collider for Blooper {
+    fn hitbox(&self) -> Hitbox {
+        self.obj.hitbox()
     }
-}
\ No newline at end of file
+}
+
+pub struct Cheep {
+    pub obj: game::Block,
+    pub to_move: bool,
+    delay: usize,
+    bob_delay: u32,
+}
+
+impl Cheep {
+    pub fn create(x: f32, y: f32) -> Self {
+        let mut obj = game::Block::create(x, y, 16.0/240.0, 16.0/256.0, false, Path::new("src/scenes/game/assets/images/cheep1.png"), "cheep");
+        obj.add_sprite(Path::new("src/scenes/game/assets/images/cheep2.png"));
+
+        obj.move_acc_x = -1.0;
+        obj.move_acc_y = 1.0;
+
+        let to_move = false;
+        let delay = 0;
+        let bob_delay = 0;
+
+        Self{obj, to_move, delay, bob_delay}
+    }
+
+    // swims left, `move_acc_y` is which way it bobs
+    pub fn handle(&mut self) {
+        self.obj.x += STEP_MS*CHEEP_SPEED*self.obj.move_acc_x;
+        self.obj.y += STEP_MS*CHEEP_BOB*self.obj.move_acc_y;
+        keep_in_water(&mut self.obj);
+
+        self.bob_delay += 1;
+        if self.bob_delay == CHEEP_BOB_STEPS {
+            self.bob_delay = 0;
+            self.obj.move_acc_y = -self.obj.move_acc_y;
+        }
+        animate(&mut self.delay, &mut self.obj.state);
+    }
+
+    pub fn sprite(&self) -> &str {
+        self.obj.sprite()
+    }
+}
+
+impl Collider for Cheep {
+    fn hitbox(&self) -> Hitbox {
+        self.obj.hitbox()
+    }
+}
@@ -1,115 +1,64 @@
-use crate::render;
 use crate::scenes::game;
+use crate::scenes::game::collision::{Collider, Hitbox};
+use crate::scenes::game::level;
+use std::f32::consts::PI;
 use std::path::Path;
-use std::ffi::{CString, c_void};
+
+// a firebar takes four seconds for a full turn, its fireballs flicker every
+// few steps
+const FIREBAR_SPEED: f32 = PI/120.0;
+const FIREBAR_FRAME_STEPS: usize = 4;
 
 pub struct Flag {
-    x: f32,
-    y: f32,
-    stone: game::Block,
-    pub objects: Vec<render::Object>,
-    textures: Vec<render::Texture>,
-    program: render::Program,
+    pub x: f32,
+    pub y: f32,
+    pub stone: game::Block,
+    pub objects: Vec<game::Block>,
+    banner: usize,
 }
 
 impl Flag {
     fn create(x: f32, y: f32) -> Self {
-        const INDCIES: [i32; 6] = [
-            0, 1, 2,
-            2, 3, 0
-        ];
-        let vert_shader = render::Shader::vertex_from_src(
-            &CString::new(include_str!("assets/shaders/block.vert")).unwrap(),
-        ).unwrap();
-        
-        let frag_shader = render::Shader::fragment_from_src(
-            &CString::new(include_str!("assets/shaders/block.frag")).unwrap(),
-        ).unwrap();
-        let program = render::Program::create_with_shaders(&[vert_shader, frag_shader]).unwrap();
-
-        let mut textures: Vec<render::Texture> = vec![];
-        let mut objects: Vec<render::Object> = vec![];
+        let mut objects: Vec<game::Block> = vec![];
 
-        let stone = game::Block::create(x, y, 16.0/240.0, 16.0/256.0, false, &Path::new("src/scenes/game/assets/images/stone_up.png"), "flag");
+        let stone = game::Block::create(x, y, 16.0/240.0, 16.0/256.0, false, Path::new("src/scenes/game/assets/images/stone_up.png"), "flag");
         
         let h = 16.0/240.0;
         let w = 16.0/256.0;
         let mut offset = 1.0;
         for _i in 0..=8 {
-            let points: Vec<f32> = vec![
-                x+w, y+(offset*h), 0.0, 1.0, 0.0,
-                x+w, y+((offset+2.0)*h), 0.0, 1.0, 1.0,
-                x-w, y+((offset+2.0)*h), 0.0, 0.0, 1.0,
-                x-w, y+(offset*h), 0.0, 0.0, 0.0
-            ];
-
-            let texture = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/flag_pipe.png"));
-            let mut obj = render::Object::create_square_with_points(points, INDCIES);
-            obj.set_cordinates(x, y+((offset+1.0)*h), 16.0/240.0, 16.0/256.0);
-            textures.push(texture);
-            objects.push(obj);
+            let pole = game::Block::create(x, y+((offset+1.0)*h), h, w, false, Path::new("src/scenes/game/assets/images/flag_pipe.png"), "flag");
+            objects.push(pole);
 
             offset += 2.0;
         }
 
         offset -= 2.0;
 
-        let points: Vec<f32> = vec![
-            x, y+(offset*h), 0.0, 1.0, 0.0,
-            x, y+((offset+2.0)*h), 0.0, 1.0, 1.0,
-            x-(2.0*w), y+((offset+2.0)*h), 0.0, 0.0, 1.0,
-            x-(2.0*w), y+(offset*h), 0.0, 0.0, 0.0
-        ];
-
-        let texture = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/flag.png"));
-        let obj = render::Object::create_square_with_points(points, INDCIES);
-        textures.push(texture);
-        objects.push(obj);
+        let banner = objects.len();
+        let flag = game::Block::create(x-w, y+((offset+1.0)*h), h, w, false, Path::new("src/scenes/game/assets/images/flag.png"), "flag");
+        objects.push(flag);
 
         offset += 2.0;
 
-        let points: Vec<f32> = vec![
-            x+w, y+(offset*h), 0.0, 1.0, 0.0,
-            x+w, y+((offset+2.0)*h), 0.0, 1.0, 1.0,
-            x-w, y+((offset+2.0)*h), 0.0, 0.0, 1.0,
-            x-w, y+(offset*h), 0.0, 0.0, 0.0
-        ];
+        let ball = game::Block::create(x, y+((offset+1.0)*h), h, w, false, Path::new("src/scenes/game/assets/images/flag_ball.png"), "flag");
+        objects.push(ball);
 
-        let texture = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/flag_ball.png"));
-        let obj = render::Object::create_square_with_points(points, INDCIES);
-        textures.push(texture);
-        objects.push(obj);
-
-        unsafe {
-            for obj in objects.iter() {
-                obj.set_vertex_attrib_pointer(0, 
-                    3, 
-                    gl::FLOAT, 
-                    gl::FALSE, 
-                    (5 * std::mem::size_of::<f32>()) as gl::types::GLint, 
-                    std::ptr::null()
-                );
-                
-                obj.set_vertex_attrib_pointer(1, 
-                    2, 
-                    gl::FLOAT, 
-                    gl::FALSE, 
-                    (5 * std::mem::size_of::<f32>()) as gl::types::GLint, 
-                    (3 * std::mem::size_of::<f32>()) as *const c_void, 
-                );
-            }
-        }
+        Self{x, y, stone, objects, banner}
+    }
 
-        Self{x, y, stone, objects, textures, program}
+    // The pole from the stone up to the ball, it's only a few pixels wide.
+    pub fn pole(&self) -> Hitbox {
+        let h = self.stone.h;
+        Hitbox{x: self.x, y: self.y+11.0*h, h: 10.0*h, w: 2.0/256.0}
     }
 
-    pub unsafe fn draw(&self) {
-        self.stone.draw();
-        for i in 0..=self.objects.len()-1 {
-            gl::BindTexture(gl::TEXTURE_2D, self.textures[i].texture);
-            gl::BindVertexArray(self.objects[i].vao);
-            gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, std::ptr::null());
-        }
+    // Lowers the flag by `step`, returns true once it's down at the stone.
+    pub fn lower(&mut self, step: f32) -> bool {
+        let bottom = self.y+2.0*self.stone.h;
+        let banner = &mut self.objects[self.banner];
+        banner.y = (banner.y-step).max(bottom);
+        banner.y == bottom
     }
 }
 
@@ -118,274 +67,134 @@ pub struct Pipe {
     pub y: f32,
     pub h: f32,
     pub w: f32,
-    with_enter: bool,
+    pub with_enter: bool,
     pub is_collision: bool,
-    pipe_len: usize,
-    pub objects: Vec<render::Object>,
-    textures: Vec<render::Texture>,
-    program: render::Program,
+    pub pipe_len: usize,
+    // side pipes are entered from the left, `x` and `y` are their opening
+    pub is_side: bool,
+    pub warp: Option<level::Warp>,
+    pub objects: Vec<game::Block>,
 }
 
 impl Pipe {
     pub fn create(x: f32, y: f32, h: f32, w: f32, pipe_len: usize, with_enter: bool, is_collision: bool) -> Self {
-        const INDCIES: [i32; 6] = [
-            0, 1, 2,
-            2, 3, 0
-        ];
-        let vert_shader = render::Shader::vertex_from_src(
-            &CString::new(include_str!("assets/shaders/block.vert")).unwrap(),
-        ).unwrap();
-        
-        let frag_shader = render::Shader::fragment_from_src(
-            &CString::new(include_str!("assets/shaders/block.frag")).unwrap(),
-        ).unwrap();
-        let program = render::Program::create_with_shaders(&[vert_shader, frag_shader]).unwrap();
-
-        let mut textures: Vec<render::Texture> = vec![];
-        let mut objects: Vec<render::Object> = vec![];
+        let mut objects: Vec<game::Block> = vec![];
 
         if with_enter {
-            let points: Vec<f32> = vec![
-                x+w, y+h, 0.0, 1.0, 0.0,
-                x+w, y-h, 0.0, 1.0, 1.0,
-                x, y-h, 0.0, 0.0, 1.0,
-                x, y+h, 0.0, 0.0, 0.0
-            ];
-    
-            let texture = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/pipe_enter_right.png"));
-            let mut obj = render::Object::create_square_with_points(points, INDCIES);
-            obj.set_cordinates(x+w/2 as f32, y, 16.0/240.0, 16.0/256.0);
-            textures.push(texture);
-            objects.push(obj);
-    
-            let points: Vec<f32> = vec![
-                x-w, y+h, 0.0, 1.0, 0.0,
-                x-w, y-h, 0.0, 1.0, 1.0,
-                x, y-h, 0.0, 0.0, 1.0,
-                x, y+h, 0.0, 0.0, 0.0
-            ];
-    
-            let texture = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/pipe_enter_left.png"));
-            let mut obj = render::Object::create_square_with_points(points, INDCIES);
-            obj.set_cordinates(x-w/2 as f32, y, 16.0/240.0, 16.0/256.0);
-            textures.push(texture);
-            objects.push(obj);
+            let enter_right = game::Block::create(x+w/2.0, y, 16.0/240.0, 16.0/256.0, false, Path::new("src/scenes/game/assets/images/pipe_enter_right.png"), "pipe");
+            objects.push(enter_right);
+
+            let enter_left = game::Block::create(x-w/2.0, y, 16.0/240.0, 16.0/256.0, false, Path::new("src/scenes/game/assets/images/pipe_enter_left.png"), "pipe");
+            objects.push(enter_left);
         }
 
         let mut offset = 1.0;
         for _i in 1..=pipe_len {    
-            let points: Vec<f32> = vec![
-                x+w, y-(offset*h), 0.0, 1.0, 0.0,
-                x+w, y-((offset+2.0)*h), 0.0, 1.0, 1.0,
-                x, y-((offset+2.0)*h), 0.0, 0.0, 1.0,
-                x, y-(offset*h), 0.0, 0.0, 0.0
-            ];
-
-            let texture = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/pipe_right.png"));
-            let mut obj = render::Object::create_square_with_points(points, INDCIES);
-            obj.set_cordinates(x+w/2 as f32, y-(offset+1.0)*h, 16.0/240.0, 16.0/256.0);
-            textures.push(texture);
-            objects.push(obj);
+            let right = game::Block::create(x+w/2.0, y-(offset+1.0)*h, 16.0/240.0, 16.0/256.0, false, Path::new("src/scenes/game/assets/images/pipe_right.png"), "pipe");
+            objects.push(right);
 
-            
-            let points: Vec<f32> = vec![
-                x-w, y-(offset*h), 0.0, 1.0, 0.0,
-                x-w, y-((offset+2.0)*h), 0.0, 1.0, 1.0,
-                x, y-((offset+2.0)*h), 0.0, 0.0, 1.0,
-                x, y-(offset*h), 0.0, 0.0, 0.0
-            ];
-
-            let texture = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/pipe_left.png"));
-            let mut obj = render::Object::create_square_with_points(points, INDCIES);
-            obj.set_cordinates(x-w/2 as f32, y-(offset+1.0)*h, 16.0/240.0, 16.0/256.0);
-            textures.push(texture);
-            objects.push(obj);
+            let left = game::Block::create(x-w/2.0, y-(offset+1.0)*h, 16.0/240.0, 16.0/256.0, false, Path::new("src/scenes/game/assets/images/pipe_left.png"), "pipe");
+            objects.push(left);
             
             offset += 2.0;
         }
-
-        unsafe {
-            for obj in objects.iter() {
-                obj.set_vertex_attrib_pointer(0, 
-                    3, 
-                    gl::FLOAT, 
-                    gl::FALSE, 
-                    (5 * std::mem::size_of::<f32>()) as gl::types::GLint, 
-                    std::ptr::null()
-                );
-                
-                obj.set_vertex_attrib_pointer(1, 
-                    2, 
-                    gl::FLOAT, 
-                    gl::FALSE, 
-                    (5 * std::mem::size_of::<f32>()) as gl::types::GLint, 
-                    (3 * std::mem::size_of::<f32>()) as *const c_void, 
-                );
-            }
-        }
         
-        Self{x, y, w, h, with_enter, is_collision, pipe_len, objects, textures, program} 
+        Self{x, y, w, h, with_enter, is_collision, pipe_len, is_side: false, warp: None, objects} 
     }
 
     pub fn create_sidepipe(x: f32, y: f32, h: f32, w: f32, mut pipe_len: usize) -> Self {
-        const INDCIES: [i32; 6] = [
-            0, 1, 2,
-            2, 3, 0
-        ];
-        let vert_shader = render::Shader::vertex_from_src(
-            &CString::new(include_str!("assets/shaders/block.vert")).unwrap(),
-        ).unwrap();
-        
-        let frag_shader = render::Shader::fragment_from_src(
-            &CString::new(include_str!("assets/shaders/block.frag")).unwrap(),
-        ).unwrap();
-        let program = render::Program::create_with_shaders(&[vert_shader, frag_shader]).unwrap();
-
-        let mut textures: Vec<render::Texture> = vec![];
-        let mut objects: Vec<render::Object> = vec![];
-
-        let points: Vec<f32> = vec![
-            x+w, y+h+h, 0.0, 1.0, 0.0,
-            x+w, y, 0.0, 1.0, 1.0,
-            x-w, y, 0.0, 0.0, 1.0,
-            x-w, y+h+h, 0.0, 0.0, 0.0
-        ];
-
-        let texture = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/pipe_side_enter_top.png"));
-        let mut obj = render::Object::create_square_with_points(points, INDCIES);
-        obj.set_cordinates(x, y+h, 16.0/240.0, 16.0/256.0);
-        textures.push(texture);
-        objects.push(obj);
-
-        let points: Vec<f32> = vec![
-            x+w, y, 0.0, 1.0, 0.0,
-            x+w, y-h-h, 0.0, 1.0, 1.0,
-            x-w, y-h-h, 0.0, 0.0, 1.0,
-            x-w, y, 0.0, 0.0, 0.0
-        ];
-
-        let texture = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/pipe_side_enter_bottom.png"));
-        let mut obj = render::Object::create_square_with_points(points, INDCIES);
-        obj.set_cordinates(x, y-h, 16.0/240.0, 16.0/256.0);
-        textures.push(texture);
-        objects.push(obj);
-
-        let points: Vec<f32> = vec![
-            x+w*3 as f32, y+h+h, 0.0, 1.0, 0.0,
-            x+w*3 as f32, y, 0.0, 1.0, 1.0,
-            x+w, y, 0.0, 0.0, 1.0,
-            x+w, y+h+h, 0.0, 0.0, 0.0
-        ];
-
-        let texture = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/pipe_side_top.png"));
-        let mut obj = render::Object::create_square_with_points(points, INDCIES);
-        obj.set_cordinates(x+w*2 as f32, y+h, 16.0/240.0, 16.0/256.0);
-        textures.push(texture);
-        objects.push(obj);
-
-        let points: Vec<f32> = vec![
-            x+w*3 as f32, y, 0.0, 1.0, 0.0,
-            x+w*3 as f32, y-h-h, 0.0, 1.0, 1.0,
-            x+w, y-h-h, 0.0, 0.0, 1.0,
-            x+w, y, 0.0, 0.0, 0.0
+        let mut objects: Vec<game::Block> = vec![];
+
+        let parts = [
+            (x, y+h, "src/scenes/game/assets/images/pipe_side_enter_top.png"),
+            (x, y-h, "src/scenes/game/assets/images/pipe_side_enter_bottom.png"),
+            (x+w*2.0, y+h, "src/scenes/game/assets/images/pipe_side_top.png"),
+            (x+w*2.0, y-h, "src/scenes/game/assets/images/pipe_side_bottom.png"),
+            (x+w*4.0, y+h, "src/scenes/game/assets/images/pipe_side_connection_top.png"),
+            (x+w*4.0, y-h, "src/scenes/game/assets/images/pipe_side_connection_bottom.png"),
+            (x+w*6.0, y+h, "src/scenes/game/assets/images/pipe_right.png"),
+            (x+w*6.0, y-h, "src/scenes/game/assets/images/pipe_right.png"),
         ];
+        for (part_x, part_y, path) in parts {
+            let part = game::Block::create(part_x, part_y, h, w, false, Path::new(path), "pipe");
+            objects.push(part);
+        }
 
-        let texture = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/pipe_side_bottom.png"));
-        let mut obj = render::Object::create_square_with_points(points, INDCIES);
-        obj.set_cordinates(x+w*2 as f32, y-h, 16.0/240.0, 16.0/256.0);
-        textures.push(texture);
-        objects.push(obj);
-
-        let points: Vec<f32> = vec![
-            x+w*5 as f32, y+h+h, 0.0, 1.0, 0.0,
-            x+w*5 as f32, y, 0.0, 1.0, 1.0,
-            x+w*3 as f32, y, 0.0, 0.0, 1.0,
-            x+w*3 as f32, y+h+h, 0.0, 0.0, 0.0
-        ];
+        let pipe = Pipe::create(x+((16.0/256.0)*5.0), y+h*(3+2*pipe_len) as f32, 16.0/240.0, 32.0/256.0, pipe_len, false, false);
 
-        let texture = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/pipe_side_connection_top.png"));
-        let obj = render::Object::create_square_with_points(points, INDCIES);
-        textures.push(texture);
-        objects.push(obj);
+        objects.extend(pipe.objects);
 
-        let points: Vec<f32> = vec![
-            x+w*5 as f32, y, 0.0, 1.0, 0.0,
-            x+w*5 as f32, y-h-h, 0.0, 1.0, 1.0,
-            x+w*3 as f32, y-h-h, 0.0, 0.0, 1.0,
-            x+w*3 as f32, y, 0.0, 0.0, 0.0
-        ];
+        pipe_len += 3;
+        let with_enter = true;
+        let is_collision = true;
 
-        let texture = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/pipe_side_connection_bottom.png"));
-        let obj = render::Object::create_square_with_points(points, INDCIES);
-        textures.push(texture);
-        objects.push(obj);
+        Self{x, y, w, h, with_enter, is_collision, pipe_len, is_side: true, warp: None, objects} 
+    }
+}
 
-        let points: Vec<f32> = vec![
-            x+w*7 as f32, y+h+h, 0.0, 1.0, 0.0,
-            x+w*7 as f32, y, 0.0, 1.0, 1.0,
-            x+w*5 as f32, y, 0.0, 0.0, 1.0,
-            x+w*5 as f32, y+h+h, 0.0, 0.0, 0.0
-        ];
+// A row of fireballs turning around the block it's fixed to. It goes through
+// everything and only hurts Mario.
+pub struct Firebar {
+    pub x: f32,
+    pub y: f32,
+    len: usize,
+    // radians per step, clockwise turns are negative
+    speed: f32,
+    angle: f32,
+    // the angle before the last step
+    previous: f32,
+    delay: usize,
+    state: usize,
+    sprites: Vec<String>,
+}
 
-        let texture = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/pipe_right.png"));
-        let obj = render::Object::create_square_with_points(points, INDCIES);
-        textures.push(texture);
-        objects.push(obj);
+impl Firebar {
+    fn create(x: f32, y: f32, len: usize, clockwise: bool) -> Self {
+        let sprites: Vec<String> = (1..=4).map(|frame| format!("src/scenes/game/assets/images/fireball{}.png", frame)).collect();
+        let speed = if clockwise {-FIREBAR_SPEED} else {FIREBAR_SPEED};
 
-        let points: Vec<f32> = vec![
-            x+w*7 as f32, y, 0.0, 1.0, 0.0,
-            x+w*7 as f32, y-h-h, 0.0, 1.0, 1.0,
-            x+w*5 as f32, y-h-h, 0.0, 0.0, 1.0,
-            x+w*5 as f32, y, 0.0, 0.0, 0.0
-        ];
+        Self{x, y, len, speed, angle: 0.0, previous: 0.0, delay: 0, state: 0, sprites}
+    }
 
-        let texture = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/pipe_right.png"));
-        let obj = render::Object::create_square_with_points(points, INDCIES);
-        textures.push(texture);
-        objects.push(obj);
+    pub fn handle(&mut self) {
+        self.angle = (self.angle+self.speed)%(2.0*PI);
+        self.delay += 1;
+        if self.delay == FIREBAR_FRAME_STEPS {
+            self.delay = 0;
+            self.state = (self.state+1)%self.sprites.len();
+        }
+    }
 
-        let pipe = Pipe::create(x+((16.0/256.0)*5 as f32), -1.0-((16.0/240.0)*(13-pipe_len) as f32), 16.0/240.0, 32.0/256.0, pipe_len, false, false);
+    pub fn snapshot(&mut self) {
+        self.previous = self.angle;
+    }
 
-        textures.extend(pipe.textures);
-        objects.extend(pipe.objects);
+    // The fireballs from the block outwards, each half a tile further out.
+    pub fn fireballs(&self) -> Vec<Hitbox> {
+        self.fireballs_at(self.angle)
+    }
 
-        unsafe {
-            for obj in objects.iter() {
-                obj.set_vertex_attrib_pointer(0, 
-                    3, 
-                    gl::FLOAT, 
-                    gl::FALSE, 
-                    (5 * std::mem::size_of::<f32>()) as gl::types::GLint, 
-                    std::ptr::null()
-                );
-                
-                obj.set_vertex_attrib_pointer(1, 
-                    2, 
-                    gl::FLOAT, 
-                    gl::FALSE, 
-                    (5 * std::mem::size_of::<f32>()) as gl::types::GLint, 
-                    (3 * std::mem::size_of::<f32>()) as *const c_void, 
-                );
-            }
+    // The fireballs `alpha` of the way through the last step's turn.
+    pub fn blended_fireballs(&self, alpha: f32) -> Vec<Hitbox> {
+        // the angle wraps around at a full turn
+        let mut turn = self.angle-self.previous;
+        if turn.abs() > PI {
+            turn -= 2.0*PI*turn.signum();
         }
+        self.fireballs_at(self.previous+turn*alpha)
+    }
 
-        pipe_len += 3;
-        let with_enter = true;
-        let is_collision = true;
-
-        Self{x, y, w, h, with_enter, is_collision, pipe_len, objects, textures, program} 
+    fn fireballs_at(&self, angle: f32) -> Vec<Hitbox> {
+        let (sin, cos) = angle.sin_cos();
+        (0..self.len).map(|index| {
+            let x = self.x+index as f32*level::TILE_W*cos;
+            let y = self.y+index as f32*level::TILE_H*sin;
+            Hitbox{x, y, h: level::TILE_H/2.0, w: level::TILE_W/2.0}
+        }).collect()
     }
 
-    pub unsafe fn draw(&self) {
-        let mut add = 0;
-        if self.with_enter {
-            add = 2;
-        }
-        for i in 0..self.pipe_len*2+add {
-            gl::BindTexture(gl::TEXTURE_2D, self.textures[i].texture);
-            gl::BindVertexArray(self.objects[i].vao);
-            gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, std::ptr::null());
-        }
+    pub fn sprite(&self) -> &str {
+        &self.sprites[self.state]
     }
 }
 
@@ -399,132 +208,69 @@ pub struct QuestionMarkBlock {
     pub state: usize,
     pub delay: i32,
     pub is_hit: bool,
-    pub obj: render::Object,
-    textures: Vec<render::Texture>,
-    program: render::Program,
+    sprites: Vec<String>,
 }
 
 impl QuestionMarkBlock {
     pub fn create(x: f32, y: f32, h: f32, w: f32, collision_event: bool, collision_name: String) -> Self {
-        let points: Vec<f32> = vec![
-            x+w, y+h, 0.0, 1.0, 0.0,
-            x+w, y-h, 0.0, 1.0, 1.0,
-            x-w, y-h, 0.0, 0.0, 1.0,
-            x-w, y+h, 0.0, 0.0, 0.0
-        ];
-
-        const INDCIES: [i32; 6] = [
-            0, 1, 2,
-            2, 3, 0
+        let sprites: Vec<String> = vec![
+            "src/scenes/game/assets/images/question_mark_block1.png".to_string(),
+            "src/scenes/game/assets/images/question_mark_block2.png".to_string(),
+            "src/scenes/game/assets/images/question_mark_block3.png".to_string(),
         ];
 
-        let mut obj = render::Object::create_square_with_points(points, INDCIES);
-        obj.set_cordinates(x, y, h, w);
-
-        let mut textures: Vec<render::Texture> = vec![];
-    
-        let texture1 = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/question_mark_block1.png"));
-        textures.push(texture1);
-        let texture2 = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/question_mark_block2.png"));
-        textures.push(texture2);
-        let texture3 = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/question_mark_block3.png"));
-        textures.push(texture3);
-
-        let vert_shader = render::Shader::vertex_from_src(
-            &CString::new(include_str!("assets/shaders/block.vert")).unwrap(),
-        ).unwrap();
-
-        let frag_shader = render::Shader::fragment_from_src(
-            &CString::new(include_str!("assets/shaders/block.frag")).unwrap(),
-        ).unwrap();
-
-        let program = render::Program::create_with_shaders(&[vert_shader, frag_shader]).unwrap();
-
-        unsafe {
-            obj.set_vertex_attrib_pointer(0, 
-                3, 
-                gl::FLOAT, 
-                gl::FALSE, 
-                (5 * std::mem::size_of::<f32>()) as gl::types::GLint, 
-                std::ptr::null()
-            );
-            
-            obj.set_vertex_attrib_pointer(1, 
-                2, 
-                gl::FLOAT, 
-                gl::FALSE, 
-                (5 * std::mem::size_of::<f32>()) as gl::types::GLint, 
-                (3 * std::mem::size_of::<f32>()) as *const c_void, 
-            );
-        }
-
         let state = 0;
         let delay = 0;
         let is_hit = false;
 
-        Self{x, y, w, h, collision_event, collision_name, state, delay, is_hit, obj, textures, program} 
+        Self{x, y, w, h, collision_event, collision_name, state, delay, is_hit, sprites} 
     }
 
-    pub fn handler(&mut self, objects: &mut Vec<game::Block>) {
+    // A mushroom block gives a fire flower to Mario once he is big. Items
+    // start inside the block and rise out of it.
+    pub fn handler(&mut self, objects: &mut Vec<game::Block>, is_small: bool) {
         if self.collision_event {
-            if self.collision_name == "mushroom" {
-                let mut obj = game::Block::create(0.0, 0.0, self.h, self.w, false, &Path::new("src/scenes/game/assets/images/mushroom.png"), "mushroom");
+            if self.collision_name == "mushroom" && is_small {
+                let mut obj = game::Block::create(self.x, self.y, self.h, self.w, false, Path::new("src/scenes/game/assets/images/mushroom.png"), "mushroom");
     
-                let vert_shader = render::Shader::vertex_from_src(
-                    &CString::new(include_str!("assets/shaders/mushroom.vert")).unwrap(),
-                ).unwrap();
-        
-                let frag_shader = render::Shader::fragment_from_src(
-                    &CString::new(include_str!("assets/shaders/mushroom.frag")).unwrap(),
-                ).unwrap();
-        
-                let program = render::Program::create_with_shaders(&[vert_shader, frag_shader]).unwrap();
+                obj.move_acc_x = 1.0;
+                objects.push(obj);
+            }else if self.collision_name == "mushroom" {
+                let obj = game::Block::create(self.x, self.y, self.h, self.w, false, Path::new("src/scenes/game/assets/images/fire_flower.png"), "flower");
+
+                objects.push(obj);
+            }else if self.collision_name == "1up" {
+                let mut obj = game::Block::create(self.x, self.y, self.h, self.w, false, Path::new("src/scenes/game/assets/images/mushroom_1up.png"), "1up");
 
-                obj.x = self.x;
-                obj.y = self.y+2.0*self.h;
-    
-                obj.program = program;
-    
                 obj.move_acc_x = 1.0;
                 objects.push(obj);
             }else {
-                let mut block = game::Block::create(self.x, self.y+2.0*self.h, self.h, 8.0/256.0, true, &Path::new("src/scenes/game/assets/images/coin1.png"), "coin");
+                let mut block = game::Block::create(self.x, self.y+2.0*self.h, self.h, 8.0/256.0, true, Path::new("src/scenes/game/assets/images/coin1.png"), "coin");
 
                 block.collision_name = "coin".to_string();
-                block.textures.push(render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/coin2.png")));
-                block.textures.push(render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/coin3.png")));
-                block.textures.push(render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/coin4.png")));
-
-                let vert_shader = render::Shader::vertex_from_src(
-                    &CString::new(include_str!("assets/shaders/coin.vert")).unwrap(),
-                ).unwrap();
-        
-                let frag_shader = render::Shader::fragment_from_src(
-                    &CString::new(include_str!("assets/shaders/coin.frag")).unwrap(),
-                ).unwrap();
-        
-                let program = render::Program::create_with_shaders(&[vert_shader, frag_shader]).unwrap();
-    
-                block.program = program;
-
-                block.x = 0.0;
-                block.y = 0.0;
+                block.add_sprite(Path::new("src/scenes/game/assets/images/coin2.png"));
+                block.add_sprite(Path::new("src/scenes/game/assets/images/coin3.png"));
+                block.add_sprite(Path::new("src/scenes/game/assets/images/coin4.png"));
 
                 block.move_acc_y = 2.5;
     
                 objects.push(block);
             }
         }
-        self.textures[0] = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/brick-still.png"));
+        self.sprites[0] = "src/scenes/game/assets/images/brick-still.png".to_string();
         self.state = 0;
         self.collision_event = false;
         self.is_hit = true;
     } 
 
-    pub unsafe fn draw(&self) {
-        gl::BindTexture(gl::TEXTURE_2D, self.textures[self.state].texture);
-        gl::BindVertexArray(self.obj.vao);
-        gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, std::ptr::null());
+    pub fn sprite(&self) -> &str {
+        &self.sprites[self.state]
+    }
+}
+
+impl Collider for QuestionMarkBlock {
+    fn hitbox(&self) -> Hitbox {
+        Hitbox{x: self.x, y: self.y, h: self.h, w: self.w}
     }
 }
 
@@ -536,6 +282,10 @@ pub struct Objects {
     pub flag: Vec<Flag>,
     pub castle: Vec<game::Block>,
     pub coins: Vec<game::Block>,
+    pub lava: Vec<game::Block>,
+    pub bridge: Vec<game::Block>,
+    pub axe: Vec<game::Block>,
+    pub firebars: Vec<Firebar>,
 }
 
 impl Objects {
@@ -547,26 +297,29 @@ impl Objects {
         let flag: Vec<Flag> = vec![];
         let castle: Vec<game::Block> = vec![];
         let coins: Vec<game::Block> = vec![];
+        let lava: Vec<game::Block> = vec![];
+        let bridge: Vec<game::Block> = vec![];
+        let axe: Vec<game::Block> = vec![];
+        let firebars: Vec<Firebar> = vec![];
 
-        Self{question_mark_blocks, blocks, stones, pipes, flag, castle, coins}
+        Self{question_mark_blocks, blocks, stones, pipes, flag, castle, coins, lava, bridge, axe, firebars}
     }
 
     pub fn create_castle(&mut self, x: f32, y: f32, size: &str) {
-        let block: game::Block;
-        if size == "small" {
-            block = game::Block::create(x, y, 80.0/240.0, 80.0/256.0, false, &Path::new("src/scenes/game/assets/images/castle_small.png"), "castle");
+        let block = if size == "small" {
+            game::Block::create(x, y, 80.0/240.0, 80.0/256.0, false, Path::new("src/scenes/game/assets/images/castle_small.png"), "castle")
         }else {
-            block = game::Block::create(x, y, 80.0/240.0, 80.0/256.0, false, &Path::new("src/scenes/game/assets/images/castle_large.png"), "castle");
-        }
+            game::Block::create(x, y, 80.0/240.0, 80.0/256.0, false, Path::new("src/scenes/game/assets/images/castle_large.png"), "castle")
+        };
 
         self.castle.push(block);
     }
 
     pub fn create_coin(&mut self, x: f32, y: f32) {
-        let mut block = game::Block::create(x, y, 16.0/240.0, 16.0/256.0, true, &Path::new("src/scenes/game/assets/images/coin_still1.png"), "coin");
+        let mut block = game::Block::create(x, y, 16.0/240.0, 16.0/256.0, true, Path::new("src/scenes/game/assets/images/coin_still1.png"), "coin");
 
-        block.textures.push(render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/coin_still2.png")));
-        block.textures.push(render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/coin_still3.png")));
+        block.add_sprite(Path::new("src/scenes/game/assets/images/coin_still2.png"));
+        block.add_sprite(Path::new("src/scenes/game/assets/images/coin_still3.png"));
 
         self.coins.push(block);
     }
@@ -577,57 +330,47 @@ impl Objects {
         self.flag.push(block);
     }
 
-    pub fn create_pipe(&mut self, x: f32, y: f32, h: f32, w: f32, pipe_len: usize, with_enter: bool, is_collision: bool) {
-        let block = Pipe::create(x, y, h, w, pipe_len, with_enter, is_collision);
-
-        self.pipes.push(block);
-    }
-
-    pub fn create_stone(&mut self, x: f32, y: f32, h: f32, w: f32) {
-        let block = game::Block::create(x, y, h, w, false, &Path::new("src/scenes/game/assets/images/stone_up.png"), "stone");
+    pub fn create_stone(&mut self, x: f32, y: f32, h: f32, w: f32, path: &str) {
+        let block = game::Block::create(x, y, h, w, false, Path::new(path), "stone");
 
         self.stones.push(block);
     }
 
-    pub fn create_block(&mut self, x: f32, y: f32, h: f32, w: f32, collision_event: bool, path: &str) {
-        let block = game::Block::create(x, y, h, w, collision_event, &Path::new(path), "block");
+    // the top row of a lava pit has waves on it
+    pub fn create_lava(&mut self, x: f32, y: f32, is_top: bool) {
+        let path = if is_top {"src/scenes/game/assets/images/lava_top.png"} else {"src/scenes/game/assets/images/lava.png"};
+        let block = game::Block::create(x, y, 16.0/240.0, 16.0/256.0, false, Path::new(path), "lava");
 
-        self.blocks.push(block);
+        self.lava.push(block);
     }
 
-    pub fn create_question_mark_block(&mut self, x: f32, y: f32, h: f32, w: f32, collision_event: bool, collision_name: String) {
-        let block = QuestionMarkBlock::create(x, y, h, w, collision_event, collision_name);
+    pub fn create_bridge(&mut self, x: f32, y: f32) {
+        let block = game::Block::create(x, y, 16.0/240.0, 16.0/256.0, false, Path::new("src/scenes/game/assets/images/bridge.png"), "bridge");
 
-        self.question_mark_blocks.push(block);
+        self.bridge.push(block);
     }
 
-    pub unsafe fn draw(&self) {
-        for coin in self.coins.iter() {
-            coin.draw();
-        }
+    pub fn create_axe(&mut self, x: f32, y: f32) {
+        let block = game::Block::create(x, y, 16.0/240.0, 16.0/256.0, false, Path::new("src/scenes/game/assets/images/axe.png"), "axe");
 
-        for castle in self.castle.iter() {
-            castle.draw();
-        }
+        self.axe.push(block);
+    }
 
-        for flag in self.flag.iter() {
-            flag.draw();
-        }
+    // the block in the middle of a firebar is an empty one
+    pub fn create_firebar(&mut self, x: f32, y: f32, len: usize, clockwise: bool) {
+        self.create_stone(x, y, 16.0/240.0, 16.0/256.0, "src/scenes/game/assets/images/brick-still.png");
+        self.firebars.push(Firebar::create(x, y, len, clockwise));
+    }
 
-        for pipe in self.pipes.iter() {
-            pipe.draw();
-        }
+    pub fn create_block(&mut self, x: f32, y: f32, h: f32, w: f32, collision_event: bool, path: &str) {
+        let block = game::Block::create(x, y, h, w, collision_event, Path::new(path), "block");
 
-        for block in self.blocks.iter() {
-            block.draw();
-        }
+        self.blocks.push(block);
+    }
 
-        for stone in self.stones.iter() {
-            stone.draw();
-        }
+    pub fn create_question_mark_block(&mut self, x: f32, y: f32, h: f32, w: f32, collision_event: bool, collision_name: String) {
+        let block = QuestionMarkBlock::create(x, y, h, w, collision_event, collision_name);
 
-        for question_mark_block in self.question_mark_blocks.iter() {
-            question_mark_block.draw();
-        }
+        self.question_mark_blocks.push(block);
     }
-}
\ No newline at end of file
+}
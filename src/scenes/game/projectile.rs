@@ -0,0 +1,115 @@
+use crate::scenes::game;
+use crate::scenes::game::collision::{self, Collider, Grid, Hitbox, Side};
+use crate::scenes::game::simulation::STEP_MS;
+use crate::scenes::game::worlds::Solid;
+use std::path::Path;
+
+// fireballs fly faster than Mario runs and hop about a tile high
+const FIREBALL_SPEED: f32 = 0.0025;
+const FIREBALL_BOUNCE: f32 = 2.0;
+const GRAVITY: f32 = 0.25;
+const FRAME_STEPS: usize = 4;
+// Bowser's fire is slower than Mario's fireballs
+const FLAME_SPEED: f32 = 0.0012;
+
+// Anything thrown that flies through the level on its own. It bounces off
+// the floor and walls and hurts Mario or the mobs, whoever didn't throw it.
+pub struct Projectile {
+    pub obj: game::Block,
+    pub hurts_mario: bool,
+    speed: f32,
+    bounce: f32,
+    gravity: f32,
+    // flames fly straight through the level
+    through_walls: bool,
+    delay: usize,
+}
+
+impl Projectile {
+    // Mario's fireball, `dir` is 1 to the right and -1 to the left.
+    pub fn fireball(x: f32, y: f32, dir: f32) -> Self {
+        let mut obj = game::Block::create(x, y, 4.0/240.0, 4.0/256.0, false, Path::new("src/scenes/game/assets/images/fireball1.png"), "fireball");
+        obj.add_sprite(Path::new("src/scenes/game/assets/images/fireball2.png"));
+        obj.add_sprite(Path::new("src/scenes/game/assets/images/fireball3.png"));
+        obj.add_sprite(Path::new("src/scenes/game/assets/images/fireball4.png"));
+
+        obj.move_acc_x = dir;
+
+        let hurts_mario = false;
+        let speed = FIREBALL_SPEED;
+        let bounce = FIREBALL_BOUNCE;
+        let gravity = GRAVITY;
+        let through_walls = false;
+        let delay = 0;
+
+        Self{obj, hurts_mario, speed, bounce, gravity, through_walls, delay}
+    }
+
+    // Bowser's fire, `dir` is 1 to the right and -1 to the left.
+    pub fn flame(x: f32, y: f32, dir: f32) -> Self {
+        let mut obj = game::Block::create(x, y, 8.0/240.0, 24.0/256.0, false, Path::new("src/scenes/game/assets/images/bowser_fire1.png"), "flame");
+        obj.add_sprite(Path::new("src/scenes/game/assets/images/bowser_fire2.png"));
+
+        obj.move_acc_x = dir;
+
+        let hurts_mario = true;
+        let speed = FLAME_SPEED;
+        let bounce = 0.0;
+        let gravity = 0.0;
+        let through_walls = true;
+        let delay = 0;
+
+        Self{obj, hurts_mario, speed, bounce, gravity, through_walls, delay}
+    }
+
+    pub fn handle(&mut self, solids: &Grid<Solid>) {
+        let obj = &mut self.obj;
+        obj.move_acc_y -= self.gravity;
+        let move_x = STEP_MS*self.speed*obj.move_acc_x;
+        let move_y = STEP_MS*0.001*obj.move_acc_y;
+
+        if self.through_walls {
+            obj.x += move_x;
+            obj.y += move_y;
+        }else {
+            Self::move_and_bounce(obj, solids, move_x, move_y, self.bounce);
+        }
+
+        self.delay += 1;
+        if self.delay == FRAME_STEPS {
+            self.delay = 0;
+            obj.state = (obj.state+1)%obj.sprites.len();
+        }
+    }
+
+    fn move_and_bounce(obj: &mut game::Block, solids: &Grid<Solid>, move_x: f32, move_y: f32, bounce: f32) {
+        let hitbox = obj.hitbox();
+        let nearby: Vec<Hitbox> = solids.query(collision::swept_area(hitbox, move_x, move_y)).iter().map(|(solid, _)| *solid).collect();
+        let (hitbox, contacts) = collision::move_and_slide(hitbox, move_x, move_y, &nearby);
+        obj.x = hitbox.x;
+        obj.y = hitbox.y;
+
+        for (_, contact) in contacts {
+            match contact.side {
+                Side::Bottom => obj.move_acc_y = bounce,
+                Side::Top => obj.move_acc_y = 0.0,
+                Side::Left | Side::Right => obj.move_acc_x = contact.normal.0,
+            }
+        }
+    }
+
+    pub fn sprite(&self) -> &str {
+        self.obj.sprite()
+    }
+
+    // the sprites look left
+    pub fn flip(&self) -> bool {
+        self.obj.move_acc_x > 0.0
+    }
+}
+
+impl Collider for Projectile {
+    fn hitbox(&self) -> Hitbox {
+        self.obj.hitbox()
+    }
+}
@@ -0,0 +1,394 @@
+use crate::render;
+use crate::scenes::game::{Block, objects, simulation};
+use crate::scenes::game::collision::Hitbox;
+use std::collections::HashMap;
+use std::path::Path;
+use std::ffi::{CString, c_void};
+
+// Draws a simulation::Simulation. Every sprite is the same unit quad scaled by
+// its half extents and moved by its position, so the simulation only has to
+// know sprite paths and hitboxes.
+pub struct Renderer {
+    quad: render::Object,
+    program: render::Program,
+    mario_program: render::Program,
+    textures: HashMap<String, render::Texture>,
+    hud: render::Texts,
+    camera_x: f32,
+    camera_y: f32,
+}
+
+impl Renderer {
+    pub fn init() -> Self {
+        let points: Vec<f32> = vec![
+            1.0, 1.0, 0.0, 1.0, 0.0,
+            1.0, -1.0, 0.0, 1.0, 1.0,
+            -1.0, -1.0, 0.0, 0.0, 1.0,
+            -1.0, 1.0, 0.0, 0.0, 0.0
+        ];
+
+        const INDCIES: [i32; 6] = [
+            0, 1, 2,
+            2, 3, 0
+        ];
+
+        let quad = render::Object::create_square_with_points(points, INDCIES);
+
+        unsafe {
+            quad.set_vertex_attrib_pointer(0,
+                3,
+                gl::FLOAT,
+                gl::FALSE,
+                (5 * std::mem::size_of::<f32>()) as gl::types::GLint,
+                std::ptr::null()
+            );
+
+            quad.set_vertex_attrib_pointer(1,
+                2,
+                gl::FLOAT,
+                gl::FALSE,
+                (5 * std::mem::size_of::<f32>()) as gl::types::GLint,
+                (3 * std::mem::size_of::<f32>()) as *const c_void,
+            );
+        }
+
+        let vert_shader = render::Shader::vertex_from_src(
+            &CString::new(include_str!("assets/shaders/sprite.vert")).unwrap(),
+        ).unwrap();
+
+        let frag_shader = render::Shader::fragment_from_src(
+            &CString::new(include_str!("assets/shaders/sprite.frag")).unwrap(),
+        ).unwrap();
+
+        let program = render::Program::create_with_shaders(&[vert_shader, frag_shader]).unwrap();
+
+        let vert_shader = render::Shader::vertex_from_src(
+            &CString::new(include_str!("assets/shaders/sprite.vert")).unwrap(),
+        ).unwrap();
+
+        let frag_shader = render::Shader::fragment_from_src(
+            &CString::new(include_str!("assets/shaders/mario.frag")).unwrap(),
+        ).unwrap();
+
+        let mario_program = render::Program::create_with_shaders(&[vert_shader, frag_shader]).unwrap();
+
+        let textures: HashMap<String, render::Texture> = HashMap::new();
+        let hud = render::Texts::init();
+        let camera_x = 0.0;
+        let camera_y = 0.0;
+
+        Self{quad, program, mario_program, textures, hud, camera_x, camera_y}
+    }
+
+    fn texture(&mut self, path: &str) -> gl::types::GLuint {
+        if !self.textures.contains_key(path) {
+            let texture = render::Texture::create_new_texture_from_file(Path::new(path));
+            self.textures.insert(path.to_string(), texture);
+        }
+        self.textures[path].texture
+    }
+
+    fn is_visible(&self, x: f32, y: f32, h: f32, w: f32) -> bool {
+        (x+self.camera_x).abs() <= 1.0+w && (y+self.camera_y).abs() <= 1.0+h
+    }
+
+    // the quad covers `at`, a sprite is as big as its hitbox
+    unsafe fn draw_quad(program: &render::Program, quad: &render::Object, texture: gl::types::GLuint, at: Hitbox, flip: bool) {
+        program.set_uniform_2f("movePos", at.x, at.y);
+        program.set_uniform_2f("size", at.w, at.h);
+        program.set_uniform_1i("flipTex", flip as i32);
+        gl::BindTexture(gl::TEXTURE_2D, texture);
+        gl::BindVertexArray(quad.vao);
+        gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, std::ptr::null());
+    }
+
+    unsafe fn draw_sprite(&mut self, path: &str, x: f32, y: f32, h: f32, w: f32, flip: bool) {
+        if !self.is_visible(x, y, h, w) {
+            return;
+        }
+        let texture = self.texture(path);
+        Self::draw_quad(&self.program, &self.quad, texture, Hitbox{x, y, h, w}, flip);
+    }
+
+    unsafe fn draw_block(&mut self, block: &Block) {
+        self.draw_sprite(block.sprite(), block.x, block.y, block.h, block.w, false);
+    }
+
+    // something that moves, `alpha` of the way from where it was before the
+    // last step to where it is now
+    unsafe fn draw_moving(&mut self, path: &str, obj: &Block, alpha: f32, flip: bool) {
+        let (from_x, from_y) = obj.previous;
+        self.draw_sprite(path, from_x+(obj.x-from_x)*alpha, from_y+(obj.y-from_y)*alpha, obj.h, obj.w, flip);
+    }
+
+    unsafe fn draw_objects(&mut self, objects: &objects::Objects) {
+        for coin in objects.coins.iter() {
+            self.draw_block(coin);
+        }
+
+        for castle in objects.castle.iter() {
+            self.draw_block(castle);
+        }
+
+        for flag in objects.flag.iter() {
+            self.draw_block(&flag.stone);
+            for obj in flag.objects.iter() {
+                self.draw_block(obj);
+            }
+        }
+
+        for pipe in objects.pipes.iter() {
+            for obj in pipe.objects.iter() {
+                self.draw_block(obj);
+            }
+        }
+
+        for block in objects.blocks.iter() {
+            self.draw_block(block);
+        }
+
+        for stone in objects.stones.iter() {
+            self.draw_block(stone);
+        }
+
+        for bridge in objects.bridge.iter() {
+            self.draw_block(bridge);
+        }
+
+        for axe in objects.axe.iter() {
+            self.draw_block(axe);
+        }
+
+        for question_mark_block in objects.question_mark_blocks.iter() {
+            let qm = question_mark_block;
+            self.draw_sprite(qm.sprite(), qm.x, qm.y, qm.h, qm.w, false);
+        }
+    }
+
+    unsafe fn draw_hud(&mut self, status: &simulation::Status) {
+        self.hud.add_text("mario".to_string(), -1.0+(8.0/256.0)*5.0, 1.0-(8.0/240.0)*5.0);
+        self.hud.add_text("x".to_string() + &status.lives.to_string(), -1.0+(8.0/256.0)*17.0, 1.0-(8.0/240.0)*5.0);
+
+        self.hud.add_text(format!("{:06}", status.score), -1.0+(8.0/256.0)*5.0, 1.0-(8.0/240.0)*7.0);
+
+        self.hud.add_text(format!("+{:02}", status.coins), -1.0+(8.0/256.0)*25.0, 1.0-(8.0/240.0)*7.0);
+
+        self.hud.add_text("world".to_string(), -1.0+(8.0/256.0)*40.0, 1.0-(8.0/240.0)*5.0);
+
+        self.hud.add_text(status.world_number.to_string() + "-" + &status.world_level.to_string(), -1.0+(8.0/256.0)*42.0, 1.0-(8.0/240.0)*7.0);
+
+        self.hud.add_text("time".to_string(), -1.0+(8.0/256.0)*55.0, 1.0-(8.0/240.0)*5.0);
+        self.hud.add_text(status.time.to_string(), -1.0+(8.0/256.0)*57.0, 1.0-(8.0/240.0)*7.0);
+
+        // the HUD is drawn in screen space
+        let identity = glm::mat4(1.0, 0.0, 0.0, 0.0,
+                                 0.0, 1.0, 0.0, 0.0,
+                                 0.0, 0.0, 1.0, 0.0,
+                                 0.0, 0.0, 0.0, 1.0);
+        self.program.set_uniform_mat4("view", &identity);
+        let texture = self.texture(&status.coin_icon);
+        Self::draw_quad(&self.program, &self.quad, texture, Hitbox{x: -1.0+(8.0/256.0)*23.0, y: 1.0-(8.0/240.0)*7.0, h: 8.0/240.0, w: 8.0/256.0}, false);
+    }
+
+    /// Before every try the world and Mario's lives are shown on black.
+    ///
+    /// # Safety
+    /// The GL context the renderer was created in has to be current.
+    pub unsafe fn draw_intermission(&mut self, status: &simulation::Status) {
+        self.clear_black();
+        self.hud.add_text("world".to_string(), -1.0+(8.0/256.0)*24.0, 1.0-(8.0/240.0)*22.0);
+        self.hud.add_text(status.world_number.to_string() + "-" + &status.world_level.to_string(), -1.0+(8.0/256.0)*36.0, 1.0-(8.0/240.0)*22.0);
+        self.hud.add_text("x".to_string(), -1.0+(8.0/256.0)*32.0, 1.0-(8.0/240.0)*28.0);
+        self.hud.add_text(status.lives.to_string(), -1.0+(8.0/256.0)*36.0, 1.0-(8.0/240.0)*28.0);
+        self.draw_hud(status);
+
+        // draw_hud left the view in screen space
+        let texture = self.texture("src/scenes/game/assets/images/mario.png");
+        Self::draw_quad(&self.program, &self.quad, texture, Hitbox{x: -1.0+(8.0/256.0)*27.0, y: 1.0-(8.0/240.0)*28.0, h: 16.0/240.0, w: 16.0/256.0}, false);
+    }
+
+    /// # Safety
+    /// The GL context the renderer was created in has to be current.
+    pub unsafe fn draw_game_over(&mut self, status: &simulation::Status) {
+        self.clear_black();
+        self.hud.add_text("game".to_string(), -1.0+(8.0/256.0)*24.0, 0.0);
+        self.hud.add_text("over".to_string(), -1.0+(8.0/256.0)*34.0, 0.0);
+        self.draw_hud(status);
+    }
+
+    /// The title goes over the level, which waits behind it.
+    ///
+    /// # Safety
+    /// The GL context the renderer was created in has to be current.
+    pub unsafe fn draw_title(&mut self) {
+        self.hud.add_text("runnner".to_string(), -1.0+(8.0/256.0)*25.0, 1.0-(8.0/240.0)*14.0);
+        self.hud.add_text("push".to_string(), -1.0+(8.0/256.0)*13.0, 1.0-(8.0/240.0)*24.0);
+        self.hud.add_text("fire".to_string(), -1.0+(8.0/256.0)*23.0, 1.0-(8.0/240.0)*24.0);
+        self.hud.add_text("to".to_string(), -1.0+(8.0/256.0)*33.0, 1.0-(8.0/240.0)*24.0);
+        self.hud.add_text("start".to_string(), -1.0+(8.0/256.0)*39.0, 1.0-(8.0/240.0)*24.0);
+    }
+
+    // a black screen at full brightness, whatever the last fade left
+    unsafe fn clear_black(&mut self) {
+        self.program.set_uniform_1f("brightness", 1.0);
+        self.mario_program.set_uniform_1f("brightness", 1.0);
+        gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+    }
+
+    unsafe fn draw_mario(&mut self, sim: &simulation::Simulation, alpha: f32) {
+        let blend = |from: f32, to: f32| from+(to-from)*alpha;
+
+        // Mario blinks while he can't be hurt
+        let mario = &sim.spirit;
+        if (mario.hurt_delay/4)%2 == 1 || sim.is_in_castle() {
+            return;
+        }
+        // with a star Mario cycles through three palettes
+        let star_phase = if mario.star_delay > 0 {1+(mario.star_delay/4)%3} else {0};
+        self.mario_program.set_uniform_1i("starPhase", star_phase as i32);
+        let texture = self.texture(mario.sprite());
+        let x = blend(sim.previous.spirit_x, mario.x);
+        let y = blend(sim.previous.spirit_y, mario.y);
+        Self::draw_quad(&self.mario_program, &self.quad, texture, Hitbox{x, y, h: mario.h, w: mario.w}, mario.flip);
+    }
+
+    /// # Safety
+    /// The GL context the renderer was created in has to be current.
+    pub unsafe fn draw(&mut self, sim: &simulation::Simulation, alpha: f32) {
+        self.draw_dimmed(sim, alpha, 1.0);
+    }
+
+    /// The level at half brightness with the pause menu over it, a mushroom
+    /// points at the selected item.
+    ///
+    /// # Safety
+    /// The GL context the renderer was created in has to be current.
+    pub unsafe fn draw_pause_menu(&mut self, sim: &simulation::Simulation, alpha: f32, items: &[&str], selected: usize) {
+        self.draw_dimmed(sim, alpha, 0.5);
+        self.program.set_uniform_1f("brightness", 1.0);
+
+        self.hud.add_text("paused".to_string(), -1.0+(8.0/256.0)*27.0, 1.0-(8.0/240.0)*16.0);
+        for (index, item) in items.iter().enumerate() {
+            self.hud.add_text(item.to_string(), -1.0+(8.0/256.0)*27.0, 1.0-(8.0/240.0)*(21+3*index) as f32);
+        }
+        // the view is still in screen space from the HUD
+        let texture = self.texture("src/scenes/game/assets/images/mushroom.png");
+        Self::draw_quad(&self.program, &self.quad, texture, Hitbox{x: -1.0+(8.0/256.0)*22.0, y: 1.0-(8.0/240.0)*(21+3*selected) as f32, h: 8.0/240.0, w: 8.0/256.0}, false);
+    }
+
+    /// What the keys do, there is nothing else to set yet.
+    ///
+    /// # Safety
+    /// The GL context the renderer was created in has to be current.
+    pub unsafe fn draw_options(&mut self) {
+        self.clear_black();
+        self.hud.add_text("controls".to_string(), -1.0+(8.0/256.0)*25.0, 1.0-(8.0/240.0)*10.0);
+        let keys = [("move", "a-d"), ("jump", "w"), ("crouch", "s"), ("fire", "space"), ("pause", "esc")];
+        for (index, (action, key)) in keys.iter().enumerate() {
+            let y = 1.0-(8.0/240.0)*(16+3*index) as f32;
+            self.hud.add_text(action.to_string(), -1.0+(8.0/256.0)*17.0, y);
+            self.hud.add_text(key.to_string(), -1.0+(8.0/256.0)*37.0, y);
+        }
+        self.hud.add_text("back".to_string(), -1.0+(8.0/256.0)*17.0, 1.0-(8.0/240.0)*36.0);
+        self.hud.add_text("space".to_string(), -1.0+(8.0/256.0)*37.0, 1.0-(8.0/240.0)*36.0);
+    }
+
+    unsafe fn draw_dimmed(&mut self, sim: &simulation::Simulation, alpha: f32, dim: f32) {
+        let blend = |from: f32, to: f32| from+(to-from)*alpha;
+
+        let brightness = sim.brightness()*dim;
+        self.program.set_uniform_1f("brightness", brightness);
+        self.mario_program.set_uniform_1f("brightness", brightness);
+
+        if sim.is_over {
+            self.clear_black();
+            return;
+        }
+
+        gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+        if sim.world.bg_color == "blue" {
+            gl::ClearColor(0.384*brightness, 0.671*brightness, 0.831*brightness, 1.0);
+        }
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+
+        self.camera_x = blend(sim.previous.screen_move_x, sim.screen_move_x);
+        self.camera_y = blend(sim.previous.screen_move_y, sim.screen_move_y);
+        let view = glm::mat4(1.0, 0.0, 0.0, self.camera_x,
+                             0.0, 1.0, 0.0, self.camera_y,
+                             0.0, 0.0, 1.0, 0.0,
+                             0.0, 0.0, 0.0, 1.0);
+        self.program.set_uniform_mat4("view", &view);
+        self.mario_program.set_uniform_mat4("view", &view);
+
+        for bg in sim.world.tiles.iter().filter_map(|tile| tile.bg.as_ref()) {
+            self.draw_sprite(bg.sprite(), bg.x, bg.y, bg.h, bg.w, false);
+        }
+
+        // behind the blocks so items rise out of them
+        for obj in sim.objects_inmove.iter() {
+            self.draw_moving(obj.sprite(), obj, alpha, false);
+        }
+        // and Mario behind the pipe he goes through
+        if sim.is_in_pipe() {
+            self.draw_mario(sim, alpha);
+        }
+
+        for tile in sim.world.tiles.iter() {
+            for stone in tile.floor.iter() {
+                self.draw_block(stone);
+            }
+
+            self.draw_objects(&tile.objects);
+        }
+
+        for obj in sim.objects_still.iter() {
+            self.draw_moving(obj.sprite(), obj, alpha, false);
+        }
+        for projectile in sim.projectiles.iter() {
+            self.draw_moving(projectile.sprite(), &projectile.obj, alpha, projectile.flip());
+        }
+
+        for goomba in sim.goombas.iter() {
+            self.draw_moving(goomba.sprite(), &goomba.obj, alpha, false);
+        }
+
+        for troopa in sim.troopas.iter() {
+            self.draw_moving(troopa.sprite(), &troopa.obj, alpha, troopa.flip());
+        }
+
+        for bowser in sim.bowsers.iter() {
+            self.draw_moving(bowser.sprite(), &bowser.obj, alpha, bowser.flip);
+        }
+
+        for blooper in sim.bloopers.iter() {
+            self.draw_moving(blooper.sprite(), &blooper.obj, alpha, false);
+        }
+
+        for cheep in sim.cheeps.iter() {
+            self.draw_moving(cheep.sprite(), &cheep.obj, alpha, false);
+        }
+
+        // over the mobs, so Bowser sinks into it, and the firebars over that
+        for tile in sim.world.tiles.iter() {
+            for lava in tile.objects.lava.iter() {
+                self.draw_block(lava);
+            }
+            for firebar in tile.objects.firebars.iter() {
+                for fireball in firebar.blended_fireballs(alpha) {
+                    self.draw_sprite(firebar.sprite(), fireball.x, fireball.y, fireball.h, fireball.w, false);
+                }
+            }
+        }
+
+        if sim.hurry_delay > 0 {
+            self.hud.add_text("hurry".to_string(), -1.0+(8.0/256.0)*25.0, 1.0-(8.0/240.0)*22.0);
+            self.hud.add_text("up".to_string(), -1.0+(8.0/256.0)*37.0, 1.0-(8.0/240.0)*22.0);
+        }
+        self.draw_hud(&sim.status());
+
+        if !sim.is_in_pipe() {
+            self.draw_mario(sim, alpha);
+        }
+    }
+}
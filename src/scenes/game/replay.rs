@@ -0,0 +1,160 @@
+use crate::scenes::game::simulation::Input;
+use std::fs;
+
+// A replay is the level, the seed and the input of every step, which is all
+// it takes to play a game again exactly. Files are plain text so they can be
+// attached to tickets. Inputs are run length encoded, one line per run: how
+// many steps and the keys held, `l` `r` `u` `d` `f` or `-` for none.
+//
+//   # runnner replay
+//   level src/scenes/game/assets/levels/1-1.map
+//   seed 1234
+//   120 -
+//   45 r
+//   12 ru
+pub struct Replay {
+    pub level: String,
+    pub seed: u64,
+    runs: Vec<(usize, Input)>,
+}
+
+const KEYS: [char; 5] = ['l', 'r', 'u', 'd', 'f'];
+
+fn keys(input: &Input) -> String {
+    let held = [input.left, input.right, input.up, input.down, input.fire];
+    let keys: String = KEYS.iter().zip(held.iter()).filter(|(_, held)| **held).map(|(key, _)| *key).collect();
+    if keys.is_empty() {
+        return "-".to_string();
+    }
+    keys
+}
+
+fn parse_keys(token: &str) -> Result<Input, String> {
+    let mut input = Input::default();
+    if token == "-" {
+        return Ok(input);
+    }
+    for key in token.chars() {
+        match key {
+            'l' => input.left = true,
+            'r' => input.right = true,
+            'u' => input.up = true,
+            'd' => input.down = true,
+            'f' => input.fire = true,
+            _ => return Err(format!("unknown key `{}`", key)),
+        }
+    }
+    Ok(input)
+}
+
+impl Replay {
+    pub fn new(level: &str, seed: u64) -> Self {
+        Self{level: level.to_string(), seed, runs: vec![]}
+    }
+
+    pub fn record(&mut self, input: Input) {
+        match self.runs.last_mut() {
+            Some((count, last)) if *last == input => *count += 1,
+            _ => self.runs.push((1, input)),
+        }
+    }
+
+    pub fn steps(&self) -> usize {
+        self.runs.iter().map(|(count, _)| count).sum()
+    }
+
+    // The input of every step in order.
+    pub fn inputs(&self) -> impl Iterator<Item = Input> + '_ {
+        self.runs.iter().flat_map(|(count, input)| (0..*count).map(move |_| *input))
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let mut out = format!("# runnner replay\nlevel {}\nseed {}\n", self.level, self.seed);
+        for (count, input) in self.runs.iter() {
+            out += &format!("{} {}\n", count, keys(input));
+        }
+        fs::write(path, out)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let src = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+
+        let mut level: Option<String> = None;
+        let mut seed: Option<u64> = None;
+        let mut runs: Vec<(usize, Input)> = vec![];
+        for (index, text) in src.lines().enumerate() {
+            let error = |message: String| format!("{}:{}: {}", path, index+1, message);
+
+            let text = text.split('#').next().unwrap();
+            let tokens: Vec<&str> = text.split_whitespace().collect();
+            match tokens[..] {
+                [] => {},
+                ["level", name] => level = Some(name.to_string()),
+                ["seed", value] => seed = Some(value.parse().map_err(|_| error(format!("expected a number, found `{}`", value)))?),
+                [count, keys] => {
+                    let count = count.parse().map_err(|_| error(format!("expected a number, found `{}`", count)))?;
+                    runs.push((count, parse_keys(keys).map_err(error)?));
+                },
+                _ => return Err(error(format!("expected `<steps> <keys>`, found `{}`", text.trim()))),
+            }
+        }
+
+        match (level, seed) {
+            (Some(level), Some(seed)) => Ok(Self{level, seed, runs}),
+            _ => Err(format!("{}: a replay needs a `level` and a `seed`", path)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(name: &str) -> String {
+        std::env::temp_dir().join(format!("runnner-{}-{}.replay", name, std::process::id())).to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn runs_break_where_the_keys_change() {
+        let idle = Input::default();
+        let right = Input{right: true, ..Input::default()};
+        let jump = Input{right: true, up: true, ..Input::default()};
+        let mut replay = Replay::new("1-1.map", 7);
+        for input in [idle, idle, idle, right, jump, jump, idle] {
+            replay.record(input);
+        }
+        assert_eq!(replay.steps(), 7);
+
+        let path = path("runs");
+        replay.save(&path).unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        assert_eq!(saved, "# runnner replay\nlevel 1-1.map\nseed 7\n3 -\n1 r\n2 ru\n1 -\n");
+
+        let loaded = Replay::load(&path).ok().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(loaded.runs == replay.runs);
+        assert!(loaded.inputs().eq(replay.inputs()));
+    }
+
+    #[test]
+    fn the_seed_and_every_key_survive_a_round_trip() {
+        let mut replay = Replay::new("levels/2-1.map", u64::MAX);
+        replay.record(Input{left: true, right: true, up: true, down: true, fire: true, pause: false});
+        let path = path("seed");
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path).ok().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!((loaded.level.as_str(), loaded.seed), ("levels/2-1.map", u64::MAX));
+        assert!(loaded.inputs().eq(replay.inputs()));
+    }
+
+    #[test]
+    fn bad_replays_point_at_their_line() {
+        let path = path("bad");
+        fs::write(&path, "level 1-1.map\nseed 1\n3 -\n2 rx\n").unwrap();
+        assert_eq!(Replay::load(&path).err().unwrap(), format!("{}:4: unknown key `x`", path));
+        fs::write(&path, "level 1-1.map\n3 -\n").unwrap();
+        assert_eq!(Replay::load(&path).err().unwrap(), format!("{}: a replay needs a `level` and a `seed`", path));
+        fs::remove_file(&path).unwrap();
+    }
+}
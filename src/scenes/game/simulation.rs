@@ -0,0 +1,1559 @@
+use crate::scenes::game::{self, Block, campaign, level, worlds, mobs, spirit, items};
+use crate::scenes::game::projectile::Projectile;
+use crate::scenes::game::spirit::Power;
+use crate::scenes::game::collision::{self, Collider, Hitbox, Side};
+use std::path::Path;
+
+// The simulation always advances by the same step, whatever the frame rate,
+// so the same inputs always play out the same way.
+pub const STEP_MS: f32 = 1000.0/60.0;
+const STEPS_PER_SECOND: u32 = 60;
+// mobs that fall this far below the camera are gone for good
+const FALL_OUT_Y: f32 = -1.5;
+// Mario can't be hurt again for two seconds after he was hurt
+const HURT_STEPS: u32 = 120;
+// a star lasts ten seconds
+const STAR_STEPS: u32 = 600;
+const LIVES: u32 = 3;
+// every hundredth coin is an extra life and the count starts over
+const COINS_PER_LIFE: u32 = 100;
+// Mario freezes for half a second before he hops off the screen
+const DEAD_STEPS: u32 = 30;
+// the timer warns Mario when he has this many seconds left, for three seconds
+const HURRY_TIME: u32 = 100;
+const HURRY_STEPS: u32 = 180;
+// points for every second left at the end of the level
+const TIME_BONUS: u32 = 50;
+// how high up the pole Mario grabs it, from the top, and what that is worth
+const FLAG_SCORES: [(f32, u32); 4] = [(0.9, 5000), (0.6, 2000), (0.35, 800), (0.15, 400)];
+const SLIDE_SPEED: f32 = 0.0008;
+// a second to go into or come out of a pipe, a third of one to fade out or in
+const PIPE_STEPS: u32 = 60;
+const FADE_STEPS: u32 = 20;
+// how many of Mario's fireballs can be around at once
+const MAX_FIREBALLS: usize = 2;
+// once the axe is taken a bridge tile falls every few steps
+const COLLAPSE_STEPS: u32 = 4;
+const BOWSER_SCORE: u32 = 5000;
+// under water Mario sinks slowly, up to a top speed, and every press of jump
+// is a stroke up that kicks for a few steps
+const WATER_GRAVITY: f32 = 0.05;
+const MAX_SINK: f32 = 0.6;
+const SWIM_STROKE: f32 = 1.2;
+const STROKE_STEPS: u32 = 12;
+// the surface of the water, nobody swims out of it
+pub const WATER_TOP_ROW: i32 = 12;
+
+// What the player holds down during one step.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Input {
+    pub left: bool,
+    pub right: bool,
+    pub up: bool,
+    pub down: bool,
+    pub fire: bool,
+    // opens the pause menu, the simulation never sees it
+    pub pause: bool,
+}
+
+// xorshift64*, anything random in the game has to come from here so a seed
+// and the inputs are enough to replay a game.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // the state must never be zero
+        let state = seed^0x9e3779b97f4a7c15;
+        Self{state: if state == 0 {1} else {state}}
+    }
+
+    pub fn next(&mut self) -> u32 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545f4914f6cdd1d) >> 32) as u32
+    }
+}
+
+// What the HUD shows, scenes without a simulation keep a copy of it.
+#[derive(Clone)]
+pub struct Status {
+    pub score: u32,
+    pub coins: u32,
+    pub lives: u32,
+    pub world_number: u32,
+    pub world_level: u32,
+    pub time: u32,
+    pub coin_icon: String,
+}
+
+// What Mario brings into a level, restarting it gives that back.
+#[derive(Clone, Copy)]
+struct Carried {
+    score: u32,
+    coins: u32,
+    lives: u32,
+    power: Power,
+}
+
+// Where Mario and the camera were before the last step, the renderer blends
+// from there to the current state. Mobs, items and projectiles keep their own
+// previous position since they come and go between steps.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Snapshot {
+    pub spirit_x: f32,
+    pub spirit_y: f32,
+    pub screen_move_x: f32,
+    pub screen_move_y: f32,
+}
+
+// Mario going through a warp pipe. He sinks into it, the screen fades out
+// and in again at the other end, where he comes out of the pipe he lands in.
+struct PipeTravel {
+    warp: level::Warp,
+    // how far Mario moves each step while he goes in and while he comes out
+    enter: (f32, f32),
+    exit: f32,
+    delay: u32,
+}
+
+// The mobs of an area as the level places them.
+struct Mobs {
+    goombas: Vec<mobs::Goomba>,
+    troopas: Vec<mobs::Troopa>,
+    bowsers: Vec<mobs::Bowser>,
+    bloopers: Vec<mobs::Blooper>,
+    cheeps: Vec<mobs::Cheep>,
+}
+
+// An area Mario isn't in, it stays as he left it until he comes back.
+struct ParkedArea {
+    name: String,
+    world: worlds::World,
+    mobs: Mobs,
+}
+
+pub struct Simulation {
+    level: level::Level,
+    // the area Mario is in, only that one is simulated and drawn
+    pub area: String,
+    pub world: worlds::World,
+    parked: Vec<ParkedArea>,
+    pub spirit: spirit::Mario,
+    pub objects_still: Vec<Block>,
+    pub objects_inmove: Vec<Block>,
+    pub goombas: Vec<mobs::Goomba>,
+    pub troopas: Vec<mobs::Troopa>,
+    pub bowsers: Vec<mobs::Bowser>,
+    pub bloopers: Vec<mobs::Blooper>,
+    pub cheeps: Vec<mobs::Cheep>,
+    pub projectiles: Vec<Projectile>,
+    delay: i32,
+    pub screen_move_x: f32,
+    pub screen_move_y: f32,
+    pub is_over: bool,
+    pub is_endlvl: bool,
+    pub hud_coin_icon: Block,
+    pub score: u32,
+    pub coins: u32,
+    pub lives: u32,
+    // counts from 1, a new try starts every time Mario loses a life
+    pub tries: u32,
+    pub world_number: u32,
+    pub world_level: u32,
+    pub time: u32,
+    pub hurry_delay: u32,
+    pub previous: Snapshot,
+    pub rng: Rng,
+    time_delay: u32,
+    fire_held: bool,
+    up_held: bool,
+    dead_delay: u32,
+    checkpoint_reached: bool,
+    carried: Carried,
+    // the tile and index of the flag Mario grabbed
+    goal: Option<(usize, usize)>,
+    // steps since the last bridge tile fell, once Mario took the axe
+    collapse: Option<u32>,
+    pipe: Option<PipeTravel>,
+}
+
+impl Simulation {    
+    pub fn init(level: &level::Level, seed: u64) -> Self {      
+        let area = level.areas[0].name.clone();
+        let world = worlds::World::init(level, &area);
+        let Mobs{goombas, troopas, bowsers, bloopers, cheeps} = Self::spawn_mobs(level, &area);
+        let parked: Vec<ParkedArea> = level.areas[1..].iter().map(|other| {
+            ParkedArea{name: other.name.clone(), world: worlds::World::init(level, &other.name), mobs: Self::spawn_mobs(level, &other.name)}
+        }).collect();
+
+        let screen_move_x = 0.0;
+        let screen_move_y = 0.0;
+        let is_over = false;
+        let is_endlvl = false;
+        let objects_still: Vec<Block> = vec![];
+        let objects_inmove: Vec<Block> = vec![];
+        let projectiles: Vec<Projectile> = vec![];
+        let delay = 0;
+
+        let score = 0;
+        let coins = 0;
+        let lives = LIVES;
+        let tries = 1;
+        // levels outside the campaign show up as 1-1
+        let (world_number, world_level) = match campaign::find(&level.name) {
+            Some(index) => (campaign::STAGES[index].world, campaign::STAGES[index].level),
+            None => (1, 1),
+        };
+        let time = level.time;
+        let hurry_delay = 0;
+
+        let mut hud_coin_icon = Block::create(-1.0+(8.0/256.0)*23.0, 1.0-(8.0/240.0)*7.0, 8.0/240.0, 8.0/256.0, false, Path::new("src/scenes/game/assets/images/coin_icon1.png"), "coin_icon");
+        hud_coin_icon.add_sprite(Path::new("src/scenes/game/assets/images/coin_icon2.png"));
+        hud_coin_icon.add_sprite(Path::new("src/scenes/game/assets/images/coin_icon3.png"));
+
+        let (start_col, start_row) = level.start;
+        let mut spirit = spirit::Mario::create(level::tile_x(start_col), level::tile_y(start_row), 16.0/240.0, 16.0/256.0, Path::new("src/scenes/game/assets/images/mario.png"));
+        spirit.is_swimming = world.is_water;
+        let previous = Snapshot{spirit_x: spirit.x, spirit_y: spirit.y, screen_move_x, screen_move_y};
+        let rng = Rng::new(seed);
+        let time_delay = 0;
+        let fire_held = false;
+        let up_held = false;
+        let dead_delay = 0;
+        let checkpoint_reached = false;
+        let carried = Carried{score, coins, lives, power: Power::Small};
+        let goal = None;
+        let collapse = None;
+        let pipe = None;
+        let level = level.clone();
+        Self{level, area, world, parked, spirit, objects_still, objects_inmove, goombas, troopas, bowsers, bloopers, cheeps, projectiles, delay, screen_move_x, screen_move_y, is_over, is_endlvl, hud_coin_icon, score, coins, lives, tries, world_number, world_level, time, hurry_delay, previous, rng, time_delay, fire_held, up_held, dead_delay, checkpoint_reached, carried, goal, collapse, pipe}
+    }
+
+    // Starts the level over after Mario lost a life. Score, coins and lives
+    // stay, everything else is as it was, and once he got to the checkpoint
+    // he starts from there.
+    fn respawn(&mut self) {
+        let mut fresh = Self::init(&self.level, 0);
+        std::mem::swap(&mut fresh.rng, &mut self.rng);
+        fresh.score = self.score;
+        fresh.coins = self.coins;
+        fresh.lives = self.lives;
+        fresh.tries = self.tries+1;
+        fresh.checkpoint_reached = self.checkpoint_reached;
+        fresh.carried = self.carried;
+
+        if let (true, Some((col, row))) = (fresh.checkpoint_reached, fresh.level.checkpoint) {
+            fresh.spirit.x = level::tile_x(col);
+            fresh.spirit.y = level::tile_y(row);
+            if fresh.spirit.x >= -0.2 {
+                fresh.screen_move_x = -0.2-fresh.spirit.x;
+            }
+            fresh.previous = fresh.snapshot();
+        }
+        *self = fresh;
+    }
+
+    fn spawn_mobs(level: &level::Level, area: &str) -> Mobs {
+        let mut goombas: Vec<mobs::Goomba> = vec![];
+        let mut troopas: Vec<mobs::Troopa> = vec![];
+        let mut bowsers: Vec<mobs::Bowser> = vec![];
+        let mut bloopers: Vec<mobs::Blooper> = vec![];
+        let mut cheeps: Vec<mobs::Cheep> = vec![];
+        for entry in level.entries_in(area) {
+            let x = level::tile_x(entry.col);
+            let y = level::tile_y(entry.row);
+            match entry.item {
+                level::Item::Goomba => goombas.push(mobs::Goomba::create(x, y)),
+                level::Item::Troopa => troopas.push(mobs::Troopa::create(x, y)),
+                level::Item::Bowser => bowsers.push(mobs::Bowser::create(x, y)),
+                level::Item::Blooper => bloopers.push(mobs::Blooper::create(x, y)),
+                level::Item::Cheep => cheeps.push(mobs::Cheep::create(x, y)),
+                _ => {},
+            }
+        }
+        Mobs{goombas, troopas, bowsers, bloopers, cheeps}
+    }
+
+    // Parks the area Mario leaves and brings back the one he goes to, with
+    // its mobs where he left them. Items and fireballs don't come along.
+    fn switch_area(&mut self, name: &str) {
+        if name == self.area {
+            return;
+        }
+        let parked = self.parked.iter_mut().find(|parked| parked.name == name).unwrap();
+        std::mem::swap(&mut self.world, &mut parked.world);
+        std::mem::swap(&mut self.goombas, &mut parked.mobs.goombas);
+        std::mem::swap(&mut self.troopas, &mut parked.mobs.troopas);
+        std::mem::swap(&mut self.bowsers, &mut parked.mobs.bowsers);
+        std::mem::swap(&mut self.bloopers, &mut parked.mobs.bloopers);
+        std::mem::swap(&mut self.cheeps, &mut parked.mobs.cheeps);
+        parked.name = std::mem::replace(&mut self.area, name.to_string());
+        self.spirit.is_swimming = self.world.is_water;
+        self.objects_inmove.clear();
+        self.objects_still.clear();
+        self.projectiles.clear();
+    }
+
+    // The furthest the camera goes right, where the area ends.
+    fn camera_limit(&self) -> f32 {
+        2.0-2.0*self.world.screens as f32
+    }
+
+    pub fn status(&self) -> Status {
+        Status{
+            score: self.score,
+            coins: self.coins,
+            lives: self.lives,
+            world_number: self.world_number,
+            world_level: self.world_level,
+            time: self.time,
+            coin_icon: self.hud_coin_icon.sprite().to_string(),
+        }
+    }
+
+    // Goes on to the next level of the campaign. Score, coins, lives and
+    // Mario's power come along, the game is over after the last level.
+    fn next_level(&mut self) {
+        // past the last stage, or a level that isn't part of the campaign
+        let stage = match campaign::find(&self.level.name).and_then(|index| campaign::STAGES.get(index+1)) {
+            Some(stage) => stage,
+            None => return self.over(),
+        };
+        let level = game::load_level(stage.path).unwrap_or_else(|| panic!("could not load {}", stage.path));
+        let carried = Carried{score: self.score, coins: self.coins, lives: self.lives, power: self.spirit.power};
+        self.enter(&level, carried);
+    }
+
+    // Starts the level over as Mario came into it, for the pause menu.
+    pub fn restart_level(&mut self) {
+        let level = self.level.clone();
+        self.enter(&level, self.carried);
+    }
+
+    fn enter(&mut self, level: &level::Level, carried: Carried) {
+        let mut fresh = Self::init(level, 0);
+        std::mem::swap(&mut fresh.rng, &mut self.rng);
+        fresh.score = carried.score;
+        fresh.coins = carried.coins;
+        fresh.lives = carried.lives;
+        fresh.spirit.set_power(carried.power);
+        fresh.carried = carried;
+        fresh.tries = self.tries+1;
+        fresh.previous = fresh.snapshot();
+        *self = fresh;
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot{spirit_x: self.spirit.x, spirit_y: self.spirit.y, screen_move_x: self.screen_move_x, screen_move_y: self.screen_move_y}
+    }
+
+    // Remembers where everything is before a step moves it, the renderer
+    // blends from there to where the step leaves it.
+    fn take_snapshot(&mut self) {
+        self.previous = self.snapshot();
+        let mobs = self.goombas.iter_mut().map(|goomba| &mut goomba.obj)
+            .chain(self.troopas.iter_mut().map(|troopa| &mut troopa.obj))
+            .chain(self.bowsers.iter_mut().map(|bowser| &mut bowser.obj))
+            .chain(self.bloopers.iter_mut().map(|blooper| &mut blooper.obj))
+            .chain(self.cheeps.iter_mut().map(|cheep| &mut cheep.obj));
+        let moving = self.objects_inmove.iter_mut()
+            .chain(self.objects_still.iter_mut())
+            .chain(self.projectiles.iter_mut().map(|projectile| &mut projectile.obj))
+            .chain(mobs);
+        for obj in moving {
+            obj.previous = (obj.x, obj.y);
+        }
+        for &(tile, index) in self.world.firebars.iter() {
+            self.world.tiles[tile].objects.firebars[index].snapshot();
+        }
+    }
+
+    // Advances the game by one STEP_MS.
+    pub fn step(&mut self, input: &Input) {
+        self.take_snapshot();
+
+        if self.is_over {
+            return;
+        }
+        // everything stands still while Mario dies or goes through a pipe
+        if self.spirit.is_dead {
+            self.handle_dead();
+            return;
+        }
+        if self.pipe.is_some() {
+            self.travel();
+            return;
+        }
+        // the flag and the bridge play out by themselves, the keys are still
+        // tracked so holding one through them isn't a fresh press afterwards
+        if self.is_endlvl {
+            self.up_held = input.up;
+            self.fire_held = input.fire;
+            self.end_level();
+            return;
+        }
+        if self.enter_pipe(input) {
+            return;
+        }
+
+        if input.left {self.move_x("left")};
+        if input.right {self.move_x("right")};
+        // under water every press of jump is a stroke, holding it doesn't swim
+        if self.world.is_water {
+            if input.up && !self.up_held {self.swim()};
+        }else if input.up {
+            self.jump();
+        }
+        self.up_held = input.up;
+        if input.down {self.crouch()} else if self.spirit.is_crouch {self.stand_up()};
+        // a fireball for every press, holding the key doesn't throw more
+        if input.fire && !self.fire_held {self.throw()};
+        self.fire_held = input.fire;
+
+        self.tick_timer();
+        if self.time == 0 {
+            self.dead();
+            return;
+        }
+        self.handle();
+    }
+
+    // The level timer counts down once a second.
+    fn tick_timer(&mut self) {
+        if self.hurry_delay > 0 {
+            self.hurry_delay -= 1;
+        }
+        self.time_delay += 1;
+        if self.time_delay < STEPS_PER_SECOND || self.time == 0 {
+            return;
+        }
+        self.time_delay = 0;
+        self.time -= 1;
+        if self.time == HURRY_TIME {
+            self.hurry_delay = HURRY_STEPS;
+        }
+    }
+
+    fn jump(&mut self) {
+        self.spirit.is_falling = true;
+        if self.spirit.move_acc_y == 0.0 {
+            self.spirit.move_acc_y = 3.0;
+        }
+    }
+
+    // A stroke pushes Mario up wherever he is, off the sea floor as well.
+    fn swim(&mut self) {
+        self.spirit.is_falling = true;
+        self.spirit.move_acc_y = SWIM_STROKE;
+        self.spirit.stroke_delay = STROKE_STEPS;
+    }
+
+    fn crouch(&mut self) {
+        self.spirit.set_crouch(true);
+    }
+
+    fn throw(&mut self) {
+        let fireballs = self.projectiles.iter().filter(|projectile| !projectile.hurts_mario).count();
+        if self.spirit.power != Power::Fire || fireballs >= MAX_FIREBALLS {
+            return;
+        }
+        // the sprite is flipped when Mario looks left
+        let dir = if self.spirit.flip {-1.0} else {1.0};
+        let fireball = Projectile::fireball(self.spirit.x+dir*self.spirit.w, self.spirit.y+self.spirit.h/2.0, dir);
+        self.projectiles.push(fireball);
+    }
+
+    // Mario stays down while there is no room above him to stand up.
+    fn stand_up(&mut self) {
+        let standing = self.spirit.standing_hitbox();
+        let blocked = self.world.solids.query(standing).iter().any(|(solid, _)| collision::overlaps(standing, *solid));
+        if !blocked {
+            self.spirit.set_crouch(false);
+        }
+    }
+
+    // Warp pipes are entered by pressing down while standing on one, side
+    // pipes by walking right into their opening. Returns true if Mario went
+    // into one.
+    fn enter_pipe(&mut self, input: &Input) -> bool {
+        if self.spirit.is_falling || !(input.down || input.right) {
+            return false;
+        }
+        let mario = self.spirit.hitbox();
+        for &(tile, index) in self.world.warps.iter() {
+            let pipe = &self.world.tiles[tile].objects.pipes[index];
+            let warp = match &pipe.warp {
+                Some(warp) => warp.clone(),
+                None => continue,
+            };
+            let enter = if pipe.is_side {
+                let is_at = (mario.y-mario.h-(pipe.y-2.0*pipe.h)).abs() < 0.01
+                    && (mario.x+mario.w-(pipe.x-pipe.w)).abs() < 0.01;
+                if !input.right || !is_at {
+                    continue;
+                }
+                (2.0*mario.w/PIPE_STEPS as f32, 0.0)
+            }else {
+                let is_on = (mario.y-mario.h-(pipe.y+pipe.h)).abs() < 0.01
+                    && (mario.x-pipe.x).abs() <= pipe.w/2.0;
+                if !input.down || !is_on {
+                    continue;
+                }
+                (0.0, -2.0*mario.h/PIPE_STEPS as f32)
+            };
+
+            self.spirit.set_crouch(false);
+            self.spirit.state = 0;
+            self.spirit.flip = false;
+            self.pipe = Some(PipeTravel{warp, enter, exit: 0.0, delay: 0});
+            return true;
+        }
+        false
+    }
+
+    // One step through a pipe: going in, fading out, arriving at the warp
+    // target, fading in and coming out.
+    fn travel(&mut self) {
+        let pipe = self.pipe.as_mut().unwrap();
+        pipe.delay += 1;
+        let delay = pipe.delay;
+        if delay <= PIPE_STEPS {
+            self.spirit.x += pipe.enter.0;
+            self.spirit.y += pipe.enter.1;
+        }else if delay == PIPE_STEPS+FADE_STEPS {
+            let warp = pipe.warp.clone();
+            self.arrive(&warp);
+        }else if delay > PIPE_STEPS+2*FADE_STEPS {
+            self.spirit.y += pipe.exit;
+            if delay == 2*PIPE_STEPS+2*FADE_STEPS || pipe.exit == 0.0 {
+                self.pipe = None;
+            }
+        }
+    }
+
+    // Puts Mario at a warp target and moves the camera into its area. If
+    // the target is on top of a pipe he starts inside it to come out.
+    fn arrive(&mut self, warp: &level::Warp) {
+        self.switch_area(&warp.area);
+        self.spirit.x = level::tile_x(warp.col);
+        // the row is where his feet are
+        self.spirit.y = level::tile_y(warp.row)-level::TILE_H+self.spirit.h;
+        self.screen_move_x = (-0.2-self.spirit.x).min(0.0).max(self.camera_limit());
+
+        let mario = self.spirit.hitbox();
+        let is_on_pipe = self.world.pipes().filter(|pipe| !pipe.is_side)
+            .any(|pipe| (mario.y-mario.h-(pipe.y+pipe.h)).abs() < 0.01 && (mario.x-pipe.x).abs() <= pipe.w);
+        if is_on_pipe {
+            self.spirit.y -= 2.0*self.spirit.h;
+            if let Some(pipe) = self.pipe.as_mut() {
+                pipe.exit = 2.0*self.spirit.h/PIPE_STEPS as f32;
+            }
+        }
+        // nothing slides over from where it was in the other area
+        self.take_snapshot();
+    }
+
+    pub fn is_in_pipe(&self) -> bool {
+        self.pipe.is_some()
+    }
+
+    // How bright the screen is, it fades to black between the two ends of a
+    // pipe.
+    pub fn brightness(&self) -> f32 {
+        let delay = match &self.pipe {
+            Some(pipe) => pipe.delay as f32,
+            None => return 1.0,
+        };
+        let from_dark = (delay-(PIPE_STEPS+FADE_STEPS) as f32).abs();
+        (from_dark/FADE_STEPS as f32).min(1.0)
+    }
+
+    // Touching the flag pole ends the level, the higher Mario grabs it the
+    // more it's worth. Returns true if he grabbed it.
+    fn grab_flag(&mut self) -> bool {
+        let mario = self.spirit.hitbox();
+        let flag = |&(tile, index): &(usize, usize)| self.world.tiles[tile].objects.flag[index].pole();
+        let (tile, index) = match self.world.flags.iter().find(|goal| collision::contact(mario, flag(goal)).is_some()) {
+            Some(goal) => *goal,
+            None => return false,
+        };
+        let pole = flag(&(tile, index));
+
+        let height = (mario.y-mario.h-(pole.y-pole.h))/(2.0*pole.h);
+        self.score += FLAG_SCORES.iter().find(|(min, _)| height >= *min).map_or(100, |(_, score)| *score);
+
+        self.spirit.set_crouch(false);
+        self.spirit.x = pole.x-pole.w-self.spirit.w;
+        self.spirit.move_acc_y = 0.0;
+        self.spirit.flip = false;
+        self.spirit.state = 0;
+        self.goal = Some((tile, index));
+        self.is_endlvl = true;
+        true
+    }
+
+    // Where Mario walks to after the flag, the door of the castle or a few
+    // tiles on if the level has none.
+    fn castle_x(&self) -> f32 {
+        match (self.world.castle_x, self.goal) {
+            (Some(castle_x), _) => castle_x,
+            (None, Some((tile, index))) => self.world.tiles[tile].objects.flag[index].x+10.0*level::TILE_W,
+            (None, None) => self.spirit.x,
+        }
+    }
+
+    pub fn is_in_castle(&self) -> bool {
+        self.is_endlvl && self.goal.is_some() && self.spirit.x >= self.castle_x()
+    }
+
+    // Touching the axe ends a castle level, the bridge falls behind Mario.
+    // Returns true if he took it.
+    fn take_axe(&mut self) -> bool {
+        let mario = self.spirit.hitbox();
+        let taken = self.world.axes.query(mario).into_iter().find(|(axe, _)| collision::contact(mario, *axe).is_some());
+        let (hitbox, tile) = match taken {
+            Some(axe) => axe,
+            None => return false,
+        };
+        self.world.axes.remove(hitbox);
+        self.world.tiles[tile].objects.axe.retain(|axe| axe.hitbox() != hitbox);
+        self.spirit.set_crouch(false);
+        self.spirit.state = 0;
+        self.collapse = Some(0);
+        self.is_endlvl = true;
+        true
+    }
+
+    // The bridge falls into the lava a tile at a time from the axe end,
+    // Bowser goes down once it's gone. Mario waits where he took the axe,
+    // if he took it in a jump he lands or falls to his death.
+    fn collapse_bridge(&mut self) {
+        self.spirit.move_acc_y -= 0.15;
+        self.move_spirit(0.0, STEP_MS*0.001*self.spirit.move_acc_y);
+        if self.falls_to_death() {
+            return;
+        }
+
+        let delay = self.collapse.as_mut().unwrap();
+        *delay += 1;
+        if !self.world.bridge.is_empty() {
+            if *delay == COLLAPSE_STEPS {
+                *delay = 0;
+                let (tile, hitbox) = self.world.bridge.pop().unwrap();
+                self.world.tiles[tile].objects.bridge.retain(|bridge| bridge.hitbox() != hitbox);
+                self.world.solids.remove(hitbox);
+            }
+            return;
+        }
+
+        let bottom = FALL_OUT_Y-self.screen_move_y;
+        for bowser in self.bowsers.iter_mut() {
+            bowser.fall();
+        }
+        self.bowsers.retain(|bowser| bowser.obj.y > bottom);
+        if self.bowsers.is_empty() {
+            self.count_time();
+        }
+    }
+
+    // Mario slides down the pole while the flag comes down, hops off to the
+    // other side and walks into the castle. The time left is counted into
+    // the score before the level ends.
+    fn end_level(&mut self) {
+        self.spirit.is_falling = false;
+        if self.collapse.is_some() {
+            return self.collapse_bridge();
+        }
+        let castle_x = self.castle_x();
+        let (tile, index) = match self.goal {
+            Some(goal) => goal,
+            None => return self.over(),
+        };
+        let flag = &mut self.world.tiles[tile].objects.flag[index];
+        let pole = flag.pole();
+        let stone = flag.stone.hitbox();
+
+        if self.spirit.x < pole.x {
+            let bottom = stone.y+stone.h+self.spirit.h;
+            self.spirit.y = (self.spirit.y-STEP_MS*SLIDE_SPEED).max(bottom);
+            if flag.lower(STEP_MS*SLIDE_SPEED) && self.spirit.y == bottom {
+                // the stone stands on the floor
+                self.spirit.x = stone.x+stone.w+self.spirit.w;
+                self.spirit.y = stone.y-stone.h+self.spirit.h;
+            }
+        }else if self.spirit.x < castle_x {
+            self.spirit.x = (self.spirit.x+STEP_MS*0.001).min(castle_x);
+            self.spirit.delay += 1;
+            if self.spirit.delay == 5 {
+                self.spirit.delay = 0;
+                self.spirit.state += 1;
+                if self.spirit.state == 4 {
+                    self.spirit.state = 0;
+                }
+            }
+            self.screen_move_x = (self.screen_move_x-STEP_MS*0.001).max(self.camera_limit());
+        }else{
+            self.count_time();
+        }
+    }
+
+    // The time left is counted into the score before the level ends.
+    fn count_time(&mut self) {
+        if self.time > 0 {
+            self.time -= 1;
+            self.score += TIME_BONUS;
+        }else{
+            self.next_level();
+        }
+    }
+
+    fn move_x(&mut self, dir: &str) {
+        if dir == "left" {
+            self.spirit.move_vel_x = 1;
+        }else{
+            self.spirit.move_vel_x = -1;
+        }
+    }
+
+    fn dead(&mut self) {
+        self.spirit.set_power(Power::Small);
+        self.spirit.sprites.push("src/scenes/game/assets/images/mario_dead.png".to_string());
+        self.spirit.state = self.spirit.sprites.len()-1;
+        self.spirit.move_acc_y = 3.0;
+        self.spirit.is_dead = true;
+    }
+
+    // Mario stops for a moment, then hops up and falls off the screen
+    // through everything and loses a life.
+    fn handle_dead(&mut self) {
+        if self.dead_delay < DEAD_STEPS {
+            self.dead_delay += 1;
+            return;
+        }
+        self.spirit.move_acc_y -= 0.15;
+        self.spirit.y += STEP_MS*0.001*self.spirit.move_acc_y;
+        if self.spirit.y < FALL_OUT_Y-self.screen_move_y {
+            self.lose_life();
+        }
+    }
+
+    fn lose_life(&mut self) {
+        self.lives -= 1;
+        if self.lives == 0 {
+            self.over();
+        }else {
+            self.respawn();
+        }
+    }
+
+    // Mario is hurt by touching a mob, he loses one power up or his life.
+    fn hurt(&mut self) {
+        if self.spirit.hurt_delay > 0 || self.spirit.star_delay > 0 {
+            return;
+        }
+        match self.spirit.power {
+            Power::Small => self.dead(),
+            Power::Super => self.spirit.set_power(Power::Small),
+            Power::Fire => self.spirit.set_power(Power::Super),
+        }
+        self.spirit.hurt_delay = HURT_STEPS;
+    }
+
+    // takes the fields it changes so it can run while the items are borrowed
+    fn take_item(name: &str, spirit: &mut spirit::Mario, lives: &mut u32, score: &mut u32) {
+        if name == "1up" {
+            *lives += 1;
+            return;
+        }
+        *score += 1000;
+        if name == "star" {
+            spirit.star_delay = STAR_STEPS;
+            return;
+        }
+        let power = match (name, spirit.power) {
+            ("mushroom", Power::Small) | ("flower", Power::Small) => Power::Super,
+            ("flower", _) => Power::Fire,
+            (_, power) => power,
+        };
+        spirit.set_power(power);
+    }
+
+    fn add_coins(&mut self, count: u32) {
+        self.coins += count;
+        self.lives += self.coins/COINS_PER_LIFE;
+        self.coins %= COINS_PER_LIFE;
+    }
+
+    fn over(&mut self) {
+        self.is_over = true;
+    }
+
+    // Sweeps Mario through the floor, stones and blocks of the overworld so a
+    // long frame can't carry him through them. Only solids near his path are
+    // looked at.
+    fn move_spirit(&mut self, move_x: f32, move_y: f32) {
+        let hitbox = self.spirit.hitbox();
+        let nearby = self.world.solids.query(collision::swept_area(hitbox, move_x, move_y));
+        let solids: Vec<Hitbox> = nearby.iter().map(|(solid, _)| *solid).collect();
+
+        let (hitbox, contacts) = collision::move_and_slide(hitbox, move_x, move_y, &solids);
+        self.spirit.x = hitbox.x;
+        self.spirit.y = hitbox.y;
+
+        for (index, contact) in contacts {
+            match contact.side {
+                Side::Bottom => {
+                    if self.spirit.move_acc_y < 0.0 {
+                        self.spirit.move_acc_y = 0.0;
+                    }
+                    self.spirit.is_falling = false;
+                },
+                Side::Top => {
+                    self.spirit.move_acc_y = -1.0;
+                    match nearby[index].1 {
+                        worlds::Solid::Block{tile, index} => {
+                            let block = &mut self.world.tiles[tile].objects.blocks[index];
+                            if block.collision_name == "star" {
+                                block.handle(&mut self.objects_inmove);
+                            }else {
+                                block.handle(&mut self.objects_still);
+                            }
+                        },
+                        worlds::Solid::Question{tile, index} => {
+                            let is_small = self.spirit.power == Power::Small;
+                            let block = &mut self.world.tiles[tile].objects.question_mark_blocks[index];
+                            if block.collision_name == "mushroom" || block.collision_name == "1up" {
+                                block.handler(&mut self.objects_inmove, is_small);
+                            }else {
+                                block.handler(&mut self.objects_still, is_small);
+                            }
+                        },
+                        worlds::Solid::Ground | worlds::Solid::Bridge => continue,
+                    }
+                    self.bump_items(nearby[index].0);
+                },
+                Side::Left | Side::Right => {},
+            }
+        }
+    }
+
+    // Items resting on a block Mario hits from below hop off it.
+    fn bump_items(&mut self, block: Hitbox) {
+        for obj in self.objects_inmove.iter_mut().filter(|obj| items::is_item(&obj.collision_name)) {
+            if collision::contact(obj.hitbox(), block).is_some_and(|contact| contact.side == Side::Bottom) {
+                items::bump(obj, self.spirit.x);
+            }
+        }
+    }
+
+    fn is_in_view(&self, obj: &Block) -> bool {
+        (obj.x+self.screen_move_x).abs() <= 1.0+obj.w && (obj.y+self.screen_move_y).abs() <= 1.0+obj.h
+    }
+
+    // Mobs stay put until they scroll into view, then walk until they fall
+    // out of the level.
+    fn handle_mobs(&mut self) {
+        for index in 0..self.goombas.len() {
+            if !self.goombas[index].to_move && self.is_in_view(&self.goombas[index].obj) {
+                self.goombas[index].to_move = true;
+            }
+        }
+        for index in 0..self.troopas.len() {
+            if !self.troopas[index].to_move && self.is_in_view(&self.troopas[index].obj) {
+                self.troopas[index].to_move = true;
+            }
+        }
+        for index in 0..self.bowsers.len() {
+            if !self.bowsers[index].to_move && self.is_in_view(&self.bowsers[index].obj) {
+                self.bowsers[index].to_move = true;
+            }
+        }
+        for index in 0..self.bloopers.len() {
+            if !self.bloopers[index].to_move && self.is_in_view(&self.bloopers[index].obj) {
+                self.bloopers[index].to_move = true;
+            }
+        }
+        for index in 0..self.cheeps.len() {
+            if !self.cheeps[index].to_move && self.is_in_view(&self.cheeps[index].obj) {
+                self.cheeps[index].to_move = true;
+            }
+        }
+
+        for goomba in self.goombas.iter_mut().filter(|goomba| goomba.to_move) {
+            goomba.handle(&self.world.solids);
+        }
+        for troopa in self.troopas.iter_mut().filter(|troopa| troopa.to_move) {
+            troopa.handle(&self.world.solids);
+        }
+        for bowser in self.bowsers.iter_mut().filter(|bowser| bowser.to_move) {
+            if let Some(flame) = bowser.handle(&self.world.solids, &mut self.rng, self.spirit.x) {
+                self.projectiles.push(flame);
+            }
+        }
+        for blooper in self.bloopers.iter_mut().filter(|blooper| blooper.to_move) {
+            blooper.handle(self.spirit.hitbox());
+        }
+        for cheep in self.cheeps.iter_mut().filter(|cheep| cheep.to_move) {
+            cheep.handle();
+        }
+
+        // mobs that walk into each other both turn around
+        let mut walkers: Vec<&mut Block> = vec![];
+        walkers.extend(self.goombas.iter_mut().filter(|goomba| goomba.to_move && !goomba.is_squash).map(|goomba| &mut goomba.obj));
+        walkers.extend(self.troopas.iter_mut().filter(|troopa| troopa.to_move && !troopa.is_squash).map(|troopa| &mut troopa.obj));
+        for i in 0..walkers.len() {
+            for j in i+1..walkers.len() {
+                if let Some(contact) = collision::contact(walkers[i].hitbox(), walkers[j].hitbox()) {
+                    if contact.side == Side::Left || contact.side == Side::Right {
+                        walkers[i].move_acc_x = contact.normal.0;
+                        walkers[j].move_acc_x = -contact.normal.0;
+                    }
+                }
+            }
+        }
+
+        self.handle_shells();
+
+        let bottom = FALL_OUT_Y-self.screen_move_y;
+        self.goombas.retain(|goomba| goomba.obj.y > bottom && !goomba.is_gone());
+        self.troopas.retain(|troopa| troopa.obj.y > bottom);
+        self.bowsers.retain(|bowser| bowser.obj.y > bottom);
+        // swimmers don't fall, they are gone once they are a screen behind
+        let left = -2.0-self.screen_move_x;
+        self.bloopers.retain(|blooper| blooper.obj.x > left);
+        self.cheeps.retain(|cheep| cheep.obj.x > left);
+    }
+
+    // Projectiles fly until they leave the screen or hit someone they can
+    // hurt. Mario's knock out mobs, the others hurt Mario.
+    fn handle_projectiles(&mut self) {
+        for projectile in self.projectiles.iter_mut() {
+            projectile.handle(&self.world.solids);
+        }
+
+        let mut is_hurt = false;
+        let mut projectiles_out = vec![false; self.projectiles.len()];
+        let mut goombas_out = vec![false; self.goombas.len()];
+        let mut troopas_out = vec![false; self.troopas.len()];
+        let mut bowsers_out = vec![false; self.bowsers.len()];
+        let mut bloopers_out = vec![false; self.bloopers.len()];
+        let mut cheeps_out = vec![false; self.cheeps.len()];
+        for (projectile, out) in self.projectiles.iter().zip(projectiles_out.iter_mut()) {
+            if !self.is_in_view(&projectile.obj) {
+                *out = true;
+                continue;
+            }
+
+            let hitbox = projectile.hitbox();
+            if projectile.hurts_mario {
+                if collision::contact(hitbox, self.spirit.hitbox()).is_some() {
+                    *out = true;
+                    is_hurt = true;
+                }
+                continue;
+            }
+
+            let goomba = self.goombas.iter().zip(goombas_out.iter_mut())
+                .find(|(goomba, out)| !**out && !goomba.is_squash && goomba.to_move && collision::contact(hitbox, goomba.hitbox()).is_some());
+            if let Some((_, goomba_out)) = goomba {
+                *goomba_out = true;
+                *out = true;
+                self.score += 100;
+                continue;
+            }
+            let troopa = self.troopas.iter().zip(troopas_out.iter_mut())
+                .find(|(troopa, out)| !**out && troopa.to_move && collision::contact(hitbox, troopa.hitbox()).is_some());
+            if let Some((_, troopa_out)) = troopa {
+                *troopa_out = true;
+                *out = true;
+                self.score += 200;
+                continue;
+            }
+            let blooper = self.bloopers.iter().zip(bloopers_out.iter_mut())
+                .find(|(blooper, out)| !**out && blooper.to_move && collision::contact(hitbox, blooper.hitbox()).is_some());
+            if let Some((_, blooper_out)) = blooper {
+                *blooper_out = true;
+                *out = true;
+                self.score += 200;
+                continue;
+            }
+            let cheep = self.cheeps.iter().zip(cheeps_out.iter_mut())
+                .find(|(cheep, out)| !**out && cheep.to_move && collision::contact(hitbox, cheep.hitbox()).is_some());
+            if let Some((_, cheep_out)) = cheep {
+                *cheep_out = true;
+                *out = true;
+                self.score += 200;
+                continue;
+            }
+            // Bowser takes a few fireballs
+            let bowser = self.bowsers.iter_mut().zip(bowsers_out.iter_mut())
+                .find(|(bowser, out)| !**out && bowser.to_move && collision::contact(hitbox, bowser.hitbox()).is_some());
+            if let Some((bowser, bowser_out)) = bowser {
+                *out = true;
+                if bowser.hit() {
+                    *bowser_out = true;
+                    self.score += BOWSER_SCORE;
+                }
+            }
+        }
+
+        let mut out = projectiles_out.into_iter();
+        self.projectiles.retain(|_| !out.next().unwrap());
+        let mut out = goombas_out.into_iter();
+        self.goombas.retain(|_| !out.next().unwrap());
+        let mut out = troopas_out.into_iter();
+        self.troopas.retain(|_| !out.next().unwrap());
+        let mut out = bowsers_out.into_iter();
+        self.bowsers.retain(|_| !out.next().unwrap());
+        let mut out = bloopers_out.into_iter();
+        self.bloopers.retain(|_| !out.next().unwrap());
+        let mut out = cheeps_out.into_iter();
+        self.cheeps.retain(|_| !out.next().unwrap());
+
+        if is_hurt {
+            self.hurt();
+        }
+    }
+
+    // A sliding shell knocks out every other mob it runs into, each one in a
+    // row is worth more.
+    fn handle_shells(&mut self) {
+        let mut goombas_out = vec![false; self.goombas.len()];
+        let mut troopas_out = vec![false; self.troopas.len()];
+        for shell in 0..self.troopas.len() {
+            if !self.troopas[shell].to_move_squash || troopas_out[shell] {
+                continue;
+            }
+            let hitbox = self.troopas[shell].hitbox();
+
+            for (index, goomba) in self.goombas.iter().enumerate() {
+                if !goombas_out[index] && !goomba.is_squash && collision::contact(hitbox, goomba.hitbox()).is_some() {
+                    goombas_out[index] = true;
+                    self.score += self.troopas[shell].knock_out();
+                }
+            }
+            let hits: Vec<usize> = self.troopas.iter().enumerate()
+                .filter(|(index, troopa)| *index != shell && !troopas_out[*index] && collision::contact(hitbox, troopa.hitbox()).is_some())
+                .map(|(index, _)| index)
+                .collect();
+            for index in hits {
+                troopas_out[index] = true;
+                self.score += self.troopas[shell].knock_out();
+            }
+        }
+
+        let mut out = goombas_out.into_iter();
+        self.goombas.retain(|_| !out.next().unwrap());
+        let mut out = troopas_out.into_iter();
+        self.troopas.retain(|_| !out.next().unwrap());
+    }
+
+    // With a star Mario knocks out every mob he touches, shells included.
+    fn handle_star_contacts(&mut self) {
+        let mario = self.spirit.hitbox();
+        let mobs = self.goombas.len()+self.troopas.len()+self.bloopers.len()+self.cheeps.len();
+        self.goombas.retain(|goomba| !goomba.to_move || goomba.is_squash || collision::contact(mario, goomba.hitbox()).is_none());
+        self.troopas.retain(|troopa| !troopa.to_move || collision::contact(mario, troopa.hitbox()).is_none());
+        self.bloopers.retain(|blooper| !blooper.to_move || collision::contact(mario, blooper.hitbox()).is_none());
+        self.cheeps.retain(|cheep| !cheep.to_move || collision::contact(mario, cheep.hitbox()).is_none());
+        self.score += 100*(mobs-self.goombas.len()-self.troopas.len()-self.bloopers.len()-self.cheeps.len()) as u32;
+    }
+
+    // Landing on a mob from above squashes it and bounces Mario off, any
+    // other touch hurts him. Squashed goombas are harmless.
+    fn handle_mob_contacts(&mut self) {
+        let mario = self.spirit.hitbox();
+        if self.spirit.star_delay > 0 {
+            self.handle_star_contacts();
+            return;
+        }
+        // his feet have to be above the middle of the mob
+        let is_stomp = |contact: &collision::Contact, mob: Hitbox| {
+            contact.side == Side::Bottom && self.spirit.move_acc_y < 0.0 && contact.depth < mob.h
+        };
+
+        let mut stomps = 0;
+        let mut is_hurt = false;
+        for goomba in self.goombas.iter_mut().filter(|goomba| goomba.to_move && !goomba.is_squash) {
+            if let Some(contact) = self.spirit.contact(goomba) {
+                if is_stomp(&contact, goomba.hitbox()) {
+                    goomba.squash();
+                    stomps += 1;
+                }else {
+                    is_hurt = true;
+                }
+            }
+        }
+        // a still shell is kicked away from Mario, a sliding one is stopped by
+        // landing on it
+        for troopa in self.troopas.iter_mut().filter(|troopa| troopa.to_move) {
+            if let Some(contact) = self.spirit.contact(troopa) {
+                let stomp = is_stomp(&contact, troopa.hitbox());
+                if troopa.is_squash && !troopa.to_move_squash {
+                    troopa.kick(if troopa.obj.x > mario.x {1.0} else {-1.0});
+                }else if !troopa.is_harmful() {
+                    continue;
+                }else if stomp && troopa.is_squash {
+                    troopa.stop();
+                    stomps += 1;
+                }else if stomp {
+                    troopa.squash();
+                    stomps += 1;
+                }else {
+                    is_hurt = true;
+                }
+            }
+        }
+
+        // Bowser and whatever swims can't be stomped
+        if self.bowsers.iter().any(|bowser| bowser.to_move && self.spirit.contact(bowser).is_some()) {
+            is_hurt = true;
+        }
+        if self.bloopers.iter().any(|blooper| blooper.to_move && self.spirit.contact(blooper).is_some()) {
+            is_hurt = true;
+        }
+        if self.cheeps.iter().any(|cheep| cheep.to_move && self.spirit.contact(cheep).is_some()) {
+            is_hurt = true;
+        }
+
+        if stomps > 0 {
+            self.score += 100*stomps;
+            self.spirit.move_acc_y = 2.0;
+            self.spirit.is_falling = true;
+        }else if is_hurt {
+            self.hurt();
+        }
+    }
+
+    // Falling into a pit is the end, there is nothing to hop out of, and lava
+    // burns right away. Returns whether Mario died.
+    fn falls_to_death(&mut self) -> bool {
+        if self.spirit.y+self.spirit.h < -1.0-self.screen_move_y || self.is_in_lava() {
+            self.dead();
+            self.spirit.move_acc_y = 0.0;
+            return true;
+        }
+        false
+    }
+
+    fn is_in_lava(&self) -> bool {
+        let mario = self.spirit.hitbox();
+        self.world.lava.query(mario).iter().any(|(lava, _)| collision::overlaps(mario, *lava))
+    }
+
+    // Firebars keep turning, any of their fireballs hurts Mario.
+    fn handle_firebars(&mut self) {
+        let mario = self.spirit.hitbox();
+        let mut is_hurt = false;
+        for &(tile, index) in self.world.firebars.iter() {
+            let firebar = &mut self.world.tiles[tile].objects.firebars[index];
+            firebar.handle();
+            is_hurt |= firebar.fireballs().into_iter().any(|fireball| collision::overlaps(mario, fireball));
+        }
+        if is_hurt {
+            self.hurt();
+        }
+    }
+
+    fn handle(&mut self) {
+        // still objects animations and collision
+        if self.delay >= 5 {
+            for obj in self.objects_still.iter_mut() {
+                if obj.collision_name == "coin" {
+                    obj.state += 1;
+                    if obj.state == 4 {
+                        obj.state = 0;
+                    }
+                }
+            }
+            for obj in self.objects_inmove.iter_mut() {
+                if obj.collision_name == "star" {
+                    obj.state += 1;
+                    if obj.state == 4 {
+                        obj.state = 0;
+                    }
+                }
+            }
+            self.delay = 0;
+        }
+        // a coin out of a block counts once it starts falling back
+        let mut popped = 0;
+        self.objects_still.retain_mut(|obj| {
+            if obj.collision_name != "coin" {
+                return true;
+            }
+            if obj.move_acc_y < 0.0 {
+                popped += 1;
+                return false;
+            }
+            obj.move_acc_y -= 0.15;
+            obj.y += STEP_MS*0.0017*obj.move_acc_y;
+            true
+        });
+        self.add_coins(popped);
+        self.score += 200*popped;
+
+        // power ups move along until Mario takes them or they fall out
+        let mario = self.spirit.hitbox();
+        let bottom = FALL_OUT_Y-self.screen_move_y;
+        let solids = &self.world.solids;
+        let (spirit, lives, score) = (&mut self.spirit, &mut self.lives, &mut self.score);
+        self.objects_inmove.retain_mut(|obj| {
+            if !items::is_item(&obj.collision_name) {
+                return true;
+            }
+            items::handle(obj, solids);
+            if collision::contact(mario, obj.hitbox()).is_some() {
+                Self::take_item(&obj.collision_name, spirit, lives, score);
+                return false;
+            }
+            obj.y >= bottom
+        });
+
+        if self.world.delay >= 10 {
+            for obj in self.world.tiles.iter_mut().flat_map(|tile| tile.objects.coins.iter_mut()) {
+                obj.state += 1;
+                if obj.state == 3 {
+                    obj.state = 0;
+                }
+            }
+            self.world.delay = 0;
+
+            self.hud_coin_icon.state += 1;
+            if self.hud_coin_icon.state == 3 {
+                self.hud_coin_icon.state = 0;
+            }
+        }
+        
+        self.world.delay += 1;
+        self.delay += 1;
+
+        // gravity, Mario stands on something only if he lands on it again
+        if self.world.is_water {
+            self.spirit.move_acc_y = (self.spirit.move_acc_y-WATER_GRAVITY).max(-MAX_SINK);
+        }else {
+            self.spirit.move_acc_y -= 0.15;
+        }
+        self.spirit.is_falling = true;
+
+        let move_y = STEP_MS*0.001*self.spirit.move_acc_y;
+        let mut move_x = 0.0;
+
+        if self.spirit.hurt_delay > 0 {
+            self.spirit.hurt_delay -= 1;
+        }
+        if self.spirit.star_delay > 0 {
+            self.spirit.star_delay -= 1;
+        }
+        if self.spirit.stroke_delay > 0 {
+            self.spirit.stroke_delay -= 1;
+        }
+
+        // moving, crouching Mario can't walk
+        if self.spirit.is_crouch {
+            self.spirit.move_vel_x = 0;
+        }
+        if self.spirit.move_vel_x != 0 {
+            self.spirit.flip = self.spirit.move_vel_x == 1;
+
+            if self.spirit.is_moving != 0 && self.spirit.is_moving != self.spirit.move_vel_x {
+                self.spirit.state = 4;
+                self.spirit.is_turn = true;
+            }
+            self.spirit.is_moving = self.spirit.move_vel_x;
+
+            move_x = -self.spirit.move_vel_x as f32*(STEP_MS*0.001);
+            self.spirit.delay += 1;
+        }else{
+            self.spirit.is_moving = 0;
+            self.spirit.state = 0;
+        }
+        self.spirit.move_vel_x = 0;
+
+        self.move_spirit(move_x, move_y);
+
+        // he can't swim out of the water
+        let surface = level::tile_y(WATER_TOP_ROW)+level::TILE_H;
+        if self.world.is_water && self.spirit.y+self.spirit.h > surface {
+            self.spirit.y = surface-self.spirit.h;
+            self.spirit.move_acc_y = self.spirit.move_acc_y.min(0.0);
+        }
+
+        if self.falls_to_death() {
+            return;
+        }
+        if let Some((col, _)) = self.level.checkpoint {
+            if self.spirit.x >= level::tile_x(col) {
+                self.checkpoint_reached = true;
+            }
+        }
+        if self.grab_flag() || self.take_axe() {
+            return;
+        }
+
+        self.handle_mobs();
+        self.handle_projectiles();
+        self.handle_mob_contacts();
+        self.handle_firebars();
+
+        //left screen side collision
+        if self.spirit.x-self.spirit.w <= -1.0-(self.screen_move_x) {
+            self.spirit.x = -1.0-(self.screen_move_x)+self.spirit.w;
+        }
+        // and the right end of the area
+        let right = 1.0-self.camera_limit();
+        if self.spirit.x+self.spirit.w >= right {
+            self.spirit.x = right-self.spirit.w;
+        }
+
+        // animation
+        if self.spirit.delay == 5  {
+            self.spirit.state += 1;
+            self.spirit.delay = 0;
+            if self.spirit.is_turn {
+                self.spirit.state = 1;
+                self.spirit.is_turn = false;
+            }
+        }
+
+        if self.spirit.state == 4 && !self.spirit.is_turn {
+            self.spirit.state = 1;
+        }
+
+        // follow Mario instead of scrolling by time, on a long frame the left
+        // screen side would drag him through walls otherwise
+        if self.spirit.x >= -0.2-(self.screen_move_x) {
+            self.screen_move_x = (-0.2-self.spirit.x).max(self.camera_limit());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(src: &str) -> level::Level {
+        level::Level::parse("test", src).ok().unwrap()
+    }
+
+    #[test]
+    fn walking_right_moves_mario_right() {
+        let level = level("screens 2\nstart 2 2\nfloor 0 31\n");
+        let mut sim = Simulation::init(&level, 0);
+        let start = sim.spirit.x;
+        let input = Input{right: true, ..Default::default()};
+        for _ in 0..120 {
+            sim.step(&input);
+        }
+        assert!(sim.spirit.x > start);
+        assert!(!sim.spirit.is_dead);
+    }
+
+    fn is_in_solid(hitbox: Hitbox, solids: &collision::Grid<worlds::Solid>) -> bool {
+        solids.query(hitbox).iter().any(|(solid, _)| collision::overlaps(hitbox, *solid))
+    }
+
+    const WALLS: &str = "screens 4\nstart 2 2\nfloor 0 63\nstones 6 2 6 3\nbricks 12 5 16 5\nquestion 14 9 coin\nstairs 24 2 4 up\nstones 36 2 36 3\nstone 44 6\n";
+
+    // a whole stalled frame's worth of walking at once still stops at the wall
+    #[test]
+    fn a_long_move_stops_at_the_wall() {
+        let level = level(WALLS);
+        let mut sim = Simulation::init(&level, 0);
+        sim.move_spirit(2.0, 0.0);
+        let wall = level::tile_x(6)-level::TILE_W;
+        assert!(sim.spirit.x+sim.spirit.w <= wall+0.001);
+        assert!(!is_in_solid(sim.spirit.hitbox(), &sim.world.solids));
+    }
+
+    #[test]
+    fn mario_never_ends_up_inside_a_solid() {
+        let level = level(WALLS);
+        let mut sim = Simulation::init(&level, 0);
+        let mut rng = Rng::new(7);
+        let mut input = Input::default();
+        for step in 0..6000 {
+            // new keys every few steps, runs of the same ones in between
+            if step%8 == 0 {
+                let keys = rng.next();
+                input = Input{left: keys.is_multiple_of(4), right: keys%4 >= 2, up: keys.is_multiple_of(3), ..Default::default()};
+            }
+            sim.step(&input);
+            assert!(!sim.spirit.is_dead);
+            assert!(!is_in_solid(sim.spirit.hitbox(), &sim.world.solids), "inside a solid at step {}", step);
+        }
+    }
+
+    // mobs and items move through the same sweep, even far faster than they do
+    #[test]
+    fn fast_mobs_stop_at_walls() {
+        let level = level(WALLS);
+        let sim = Simulation::init(&level, 0);
+        let mut goomba = mobs::Goomba::create(level::tile_x(3), level::tile_y(2));
+        let mut mushroom = Block::create(level::tile_x(4), level::tile_y(2), level::TILE_H, level::TILE_W, false, Path::new("src/scenes/game/assets/images/mushroom.png"), "mushroom");
+        goomba.obj.move_acc_x = 1.0;
+        mushroom.move_acc_x = 1.0;
+        for _ in 0..60 {
+            mobs::walk(&mut goomba.obj, &sim.world.solids, 0.05);
+            mobs::walk(&mut mushroom, &sim.world.solids, 0.05);
+            assert!(!is_in_solid(goomba.hitbox(), &sim.world.solids));
+            assert!(!is_in_solid(mushroom.hitbox(), &sim.world.solids));
+            assert!(goomba.obj.x < level::tile_x(6) && mushroom.x < level::tile_x(6));
+        }
+    }
+
+    #[test]
+    fn touching_items_takes_them() {
+        let mut sim = Simulation::init(&level(WALLS), 0);
+        let (x, y) = (sim.spirit.x, sim.spirit.y);
+        for name in ["1up", "star", "mushroom"] {
+            let item = Block::create(x+level::TILE_W, y, level::TILE_H, level::TILE_W, false, Path::new("src/scenes/game/assets/images/mushroom.png"), name);
+            sim.objects_inmove.push(item);
+        }
+        let lives = sim.lives;
+        sim.handle();
+        assert!(sim.objects_inmove.iter().all(|obj| !items::is_item(&obj.collision_name)));
+        assert_eq!(sim.lives, lives+1);
+        assert_eq!(sim.score, 2000);
+        assert!(sim.spirit.star_delay > 0);
+        assert_eq!(sim.spirit.power, Power::Super);
+    }
+
+    #[test]
+    fn keys_do_nothing_while_the_level_ends() {
+        let mut sim = Simulation::init(&level(WALLS), 0);
+        sim.spirit.set_power(Power::Fire);
+        sim.is_endlvl = true;
+        sim.collapse = Some(0);
+        let x = sim.spirit.x;
+        let held = Input{right: true, fire: true, ..Input::default()};
+        for _ in 0..30 {
+            sim.step(&held);
+        }
+        assert_eq!(sim.spirit.x, x);
+        assert!(sim.projectiles.is_empty());
+        // still holding fire afterwards doesn't throw either
+        sim.is_endlvl = false;
+        sim.collapse = None;
+        sim.step(&held);
+        assert!(sim.projectiles.is_empty());
+    }
+
+    #[test]
+    fn a_hundred_coins_are_a_life() {
+        let mut sim = Simulation::init(&level(WALLS), 0);
+        sim.add_coins(99);
+        assert_eq!((sim.coins, sim.lives), (99, LIVES));
+        sim.add_coins(3);
+        assert_eq!((sim.coins, sim.lives), (2, LIVES+1));
+    }
+
+    #[test]
+    fn the_campaign_ends_after_its_last_stage() {
+        let first = game::load_level(campaign::STAGES[0].path).unwrap();
+        let mut sim = Simulation::init(&first, 0);
+        sim.next_level();
+        assert!(!sim.is_over);
+        assert_eq!(sim.level.name, campaign::STAGES[1].name());
+
+        let last = game::load_level(campaign::STAGES[campaign::STAGES.len()-1].path).unwrap();
+        let mut sim = Simulation::init(&last, 0);
+        sim.next_level();
+        assert!(sim.is_over);
+    }
+
+    #[test]
+    fn the_flag_and_castle_are_found_on_any_screen() {
+        let mut sim = Simulation::init(&level("screens 4\nstart 2 2\nfloor 0 63\nflag 50 2\ncastle 56 2 small\n"), 0);
+        sim.spirit.x = level::tile_x(47);
+        let grabbed = (0..120).any(|_| {
+            sim.step(&Input{right: true, up: true, ..Input::default()});
+            sim.goal.is_some()
+        });
+        assert!(grabbed && sim.is_endlvl);
+        let castle = &sim.world.tiles[3].objects.castle[0];
+        assert_eq!(sim.castle_x(), castle.x);
+    }
+
+    #[test]
+    fn the_bridge_falls_from_the_axe_end() {
+        let mut sim = Simulation::init(&level("screens 3\nstart 2 2\nfloor 0 9\nlava 10 40\nbridge 10 4 40\nfloor 41 47\naxe 44 2\n"), 0);
+        sim.spirit.x = level::tile_x(42);
+        let taken = (0..60).any(|_| {
+            sim.step(&Input{right: true, ..Input::default()});
+            sim.collapse.is_some()
+        });
+        assert!(taken && sim.world.tiles[2].objects.axe.is_empty());
+
+        sim.collapse = Some(0);
+        for _ in 0..COLLAPSE_STEPS {
+            sim.step(&Input::default());
+        }
+        let last = |sim: &Simulation| sim.world.tiles[0].objects.bridge.iter().map(|bridge| bridge.x).fold(-1.0, f32::max);
+        assert_eq!(sim.world.tiles[0].objects.bridge.len(), 30);
+        assert_eq!(last(&sim), level::tile_x(39));
+        for _ in 0..40*COLLAPSE_STEPS {
+            sim.step(&Input::default());
+        }
+        assert!(sim.world.tiles[0].objects.bridge.is_empty());
+        let span = Hitbox{x: level::tile_x(25), y: level::tile_y(4), h: level::TILE_H, w: 15.0*2.0*level::TILE_W};
+        assert!(sim.world.solids.query(span).iter().all(|(solid, _)| !collision::overlaps(span, *solid)));
+    }
+
+    #[test]
+    fn taking_the_axe_over_lava_still_burns() {
+        let mut sim = Simulation::init(&level("screens 2\nstart 2 2\nfloor 0 9\nlava 10 31\n"), 0);
+        sim.spirit.x = level::tile_x(14);
+        sim.spirit.y = level::tile_y(6);
+        sim.is_endlvl = true;
+        sim.collapse = Some(0);
+        let burns = (0..120).any(|_| {
+            sim.step(&Input::default());
+            sim.spirit.is_dead
+        });
+        assert!(burns);
+    }
+
+    #[test]
+    fn mobs_remember_where_a_step_started() {
+        let mut sim = Simulation::init(&level(WALLS), 0);
+        let mut goomba = mobs::Goomba::create(level::tile_x(3), level::tile_y(2));
+        goomba.to_move = true;
+        sim.goombas = vec![goomba];
+        for _ in 0..10 {
+            let before = (sim.goombas[0].obj.x, sim.goombas[0].obj.y);
+            sim.step(&Input::default());
+            assert_eq!(sim.goombas[0].obj.previous, before);
+        }
+        assert!(sim.goombas[0].obj.x != level::tile_x(3));
+    }
+
+    #[test]
+    fn the_same_seed_and_keys_play_out_the_same() {
+        for stage in [0, 3] {
+            let level = game::load_level(campaign::STAGES[stage].path).unwrap();
+            let mut sims = [Simulation::init(&level, 42), Simulation::init(&level, 42)];
+            let mut rng = Rng::new(3);
+            let mut input = Input::default();
+            for step in 0..3000 {
+                if step%10 == 0 {
+                    let keys = rng.next();
+                    input = Input{left: keys.is_multiple_of(5), right: keys%5 >= 2, up: keys.is_multiple_of(3), fire: keys.is_multiple_of(7), ..Default::default()};
+                }
+                for sim in sims.iter_mut() {
+                    sim.step(&input);
+                }
+                let [a, b] = &sims;
+                assert!(a.snapshot() == b.snapshot(), "stage {} differs at step {}", stage, step);
+                assert!(a.score == b.score && a.lives == b.lives && a.time == b.time);
+                let mobs = |sim: &Simulation| sim.goombas.iter().map(|goomba| (goomba.obj.x, goomba.obj.y))
+                    .chain(sim.bowsers.iter().map(|bowser| (bowser.obj.x, bowser.obj.y)))
+                    .collect::<Vec<(f32, f32)>>();
+                assert!(mobs(a) == mobs(b));
+            }
+        }
+    }
+}
@@ -1,7 +1,34 @@
-use crate::render;
 use std::path::Path;
-use std::ffi::{CString, c_void};
-use crate::scenes::{game, game::objects};
+use crate::scenes::game::collision::{Collider, Hitbox};
+
+// half the height of small Mario, super Mario is twice as tall
+const SMALL_H: f32 = 16.0/240.0;
+const SUPER_H: f32 = 32.0/240.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Power {
+    Small,
+    Super,
+    Fire,
+}
+
+// Standing, three walking frames, turning, jumping, crouching and two
+// swimming frames.
+fn sprites(power: Power) -> Vec<String> {
+    let name = match power {
+        Power::Small => "mario",
+        Power::Super => "mario_super",
+        Power::Fire => "mario_fire",
+    };
+    let mut sprites: Vec<String> = ["", "_move1", "_move2", "_move3", "_turn", "_jump", "_crouch", "_swim1", "_swim2"].iter()
+        .map(|frame| format!("src/scenes/game/assets/images/{}{}.png", name, frame))
+        .collect();
+    // small Mario can't crouch
+    if power == Power::Small {
+        sprites[6] = sprites[0].clone();
+    }
+    sprites
+}
 
 pub struct Mario {
     pub x: f32,
@@ -14,76 +41,26 @@ pub struct Mario {
     pub is_moving: i32,
     pub is_turn: bool,
     pub is_crouch: bool,
-    pub is_underground: bool,
     pub delay: i32,
     pub move_vel_x: i32,
     pub move_acc_y: f32,
-    pub obj: render::Object,
-    pub textures: Vec<render::Texture>,
+    pub sprites: Vec<String>,
     pub flip: bool,
-    pub program: render::Program,
+    pub power: Power,
+    // steps left in which Mario can't be hurt again
+    pub hurt_delay: u32,
+    // steps left of the star, Mario knocks out whatever he touches meanwhile
+    pub star_delay: u32,
+    // under water Mario swims instead of jumping
+    pub is_swimming: bool,
+    // steps left of the kick of his last swim stroke
+    pub stroke_delay: u32,
 }
 
 impl Mario {
     pub fn create(x: f32, y: f32, h: f32, w: f32, path: &Path) -> Self {
-        let points: Vec<f32> = vec![
-            x+w, y+h, 0.0, 1.0, 0.0,
-            x+w, y-h, 0.0, 1.0, 1.0,
-            x-w, y-h, 0.0, 0.0, 1.0,
-            x-w, y+h, 0.0, 0.0, 0.0
-        ];
-
-        const INDCIES: [i32; 6] = [
-            0, 1, 2,
-            2, 3, 0
-        ];
-
-        let obj = render::Object::create_square_with_points(points, INDCIES);
-
-        let mut textures: Vec<render::Texture> = vec![];
-
-        let texture0 = render::Texture::create_new_texture_from_file(path);
-        textures.push(texture0);
-        let texture1 = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/mario_move1.png"));
-        textures.push(texture1);
-        let texture2 = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/mario_move2.png"));
-        textures.push(texture2);
-        let texture3 = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/mario_move3.png"));
-        textures.push(texture3);
-
-        let texture_turn = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/mario_turn.png"));
-        textures.push(texture_turn);
-
-        let texture_jump = render::Texture::create_new_texture_from_file(&Path::new("src/scenes/game/assets/images/mario_jump.png"));
-        textures.push(texture_jump);
-
-        unsafe {
-            obj.set_vertex_attrib_pointer(0, 
-                3, 
-                gl::FLOAT, 
-                gl::FALSE, 
-                (5 * std::mem::size_of::<f32>()) as gl::types::GLint, 
-                std::ptr::null()
-            );
-            
-            obj.set_vertex_attrib_pointer(1, 
-                2, 
-                gl::FLOAT, 
-                gl::FALSE, 
-                (5 * std::mem::size_of::<f32>()) as gl::types::GLint, 
-                (3 * std::mem::size_of::<f32>()) as *const c_void, 
-            );
-        }
-        
-        let vert_shader = render::Shader::vertex_from_src(
-            &CString::new(include_str!("assets/shaders/mario.vert")).unwrap(),
-        ).unwrap();
-
-        let frag_shader = render::Shader::fragment_from_src(
-            &CString::new(include_str!("assets/shaders/mario.frag")).unwrap(),
-        ).unwrap();
-
-        let program = render::Program::create_with_shaders(&[vert_shader, frag_shader]).unwrap();
+        let mut sprites = sprites(Power::Small);
+        sprites[0] = path.to_string_lossy().into_owned();
 
         let move_vel_x = 0;
         let state = 0;
@@ -92,36 +69,62 @@ impl Mario {
         let is_moving = 0;
         let is_turn = false;
         let is_crouch = false;
-        let is_underground = false;
         let delay = 0;
         let move_acc_y = 0.0;
         let flip = false;
+        let power = Power::Small;
+        let hurt_delay = 0;
+        let star_delay = 0;
+        let is_swimming = false;
+        let stroke_delay = 0;
          
-        Self{x, y, h, w, state, is_falling, is_dead, is_moving, is_turn, is_crouch, is_underground, delay, move_vel_x, move_acc_y, obj, textures, flip, program}
+        Self{x, y, h, w, state, is_falling, is_dead, is_moving, is_turn, is_crouch, delay, move_vel_x, move_acc_y, sprites, flip, power, hurt_delay, star_delay, is_swimming, stroke_delay}
     }
 
-    pub fn check_hitbox(&self, obj: &game::Block) -> &str {
-        let right: bool = self.x+self.w >= obj.x-obj.w;
-        let left: bool = obj.x+obj.w >= self.x-self.w;
-        let top: bool = self.y+self.h >= obj.y-obj.h;
-        let bottom: bool = obj.y+obj.h >= self.y-self.h;
-        if bottom && left && right && top {
-            if obj.y < self.y {return "bottom"}
-            if obj.y > self.y && (obj.x-obj.w < self.x && self.x < obj.x+obj.w) {return "top"}
-            if obj.x < self.x {return "left"}
-            if obj.x > self.x {return "right"};
+    pub fn sprite(&self) -> &str {
+        if self.is_swimming && self.is_falling && !self.is_dead {
+            return &self.sprites[if self.stroke_delay > 0 {8} else {7}];
+        }
+        if self.is_falling && !self.is_dead {
+            return &self.sprites[5];
+        }
+        if self.is_crouch {
+            return &self.sprites[6];
         }
-        return "nil"
+        &self.sprites[self.state]
     }
 
-    pub unsafe fn draw(&self) {
-        self.program.set_active();
-        if self.is_falling && !self.is_dead {
-            gl::BindTexture(gl::TEXTURE_2D, self.textures[5].texture);
-        }else {
-            gl::BindTexture(gl::TEXTURE_2D, self.textures[self.state].texture);
+    pub fn set_power(&mut self, power: Power) {
+        self.power = power;
+        self.sprites = sprites(power);
+        if power == Power::Small {
+            self.is_crouch = false;
         }
-        gl::BindVertexArray(self.obj.vao);
-        gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, std::ptr::null());
+        self.resize();
+    }
+
+    // Only super Mario can crouch, it makes him as small as small Mario.
+    pub fn set_crouch(&mut self, is_crouch: bool) {
+        self.is_crouch = is_crouch && self.power != Power::Small;
+        self.resize();
     }
-}
\ No newline at end of file
+
+    // The hitbox Mario would have standing up.
+    pub fn standing_hitbox(&self) -> Hitbox {
+        let h = if self.power == Power::Small {SMALL_H} else {SUPER_H};
+        Hitbox{x: self.x, y: self.y+h-self.h, h, w: self.w}
+    }
+
+    // grows or shrinks from the feet up
+    fn resize(&mut self) {
+        let h = if self.is_crouch {SMALL_H} else {self.standing_hitbox().h};
+        self.y += h-self.h;
+        self.h = h;
+    }
+}
+
+impl Collider for Mario {
+    fn hitbox(&self) -> Hitbox {
+        Hitbox{x: self.x, y: self.y, h: self.h, w: self.w}
+    }
+}
@@ -0,0 +1,441 @@
+use crate::scenes::game::level::{self, Entry, Item, Level, LevelError};
+use std::collections::{HashMap, HashSet};
+
+// Tile maps draw a level as ASCII grids, one character per 16x16 tile. The
+// header lines are the same as in .lvl files, every area is a grid between
+// `map` and `end` whose last line is row 0. Short lines are padded with
+// empty tiles.
+//
+//   .  empty                    =  floor, always the bottom two rows
+//   #  stone                    B  brick
+//   b  brick with a coin        *  brick with a star
+//   ?  question block, coin     M  question block, mushroom
+//   o  coin                     P  pipe
+//   S  side pipe                F  flag, `|` is its pole
+//   g  goomba                   k  troopa
+//   c  small castle             C  large castle, at its bottom left corner
+//   U  question block, 1-up     ~  lava, always the bottom two rows
+//   -  bridge                   A  axe
+//   x  firebar, 6 long and cw   W  Bowser, at his bottom left corner
+//   q  blooper                  f  Cheep Cheep
+//
+// What a character can't say goes below the grid. A pipe is named by its top
+// left tile, a side pipe by its bottom left tile.
+//
+//   warp <col> <row> <area> <to_col> <to_row>   the pipe leads to area
+//   count <col> <row> <n>                       the brick holds n coins
+//   firebar <col> <row> <len> <cw | ccw>        the firebar's length and turn
+//
+// Exporting a level draws its stairs as stones, so a level read back from
+// its export has stones where the stairs were. It plays the same.
+
+const FLAG_HEIGHT: i32 = 10;
+const FIREBAR_LEN: usize = 6;
+const MIN_ROWS: i32 = 15;
+
+fn entry(area: &str, line: usize, col: i32, row: i32, item: Item) -> Entry {
+    Entry{line, area: area.to_string(), col, row, item}
+}
+
+// all tiles with the same character connected to `start`
+fn flood(cells: &HashMap<(i32, i32), char>, start: (i32, i32)) -> Vec<(i32, i32)> {
+    let c = cells[&start];
+    let mut region: Vec<(i32, i32)> = vec![];
+    let mut seen: HashSet<(i32, i32)> = HashSet::new();
+    let mut stack = vec![start];
+    while let Some((col, row)) = stack.pop() {
+        if cells.get(&(col, row)) != Some(&c) || !seen.insert((col, row)) {
+            continue;
+        }
+        region.push((col, row));
+        stack.extend([(col-1, row), (col+1, row), (col, row-1), (col, row+1)]);
+    }
+    region.sort();
+    region
+}
+
+// The pipe or side pipe whose top left tile is `start`. Only tiles of its
+// own two column body belong to it, so pipes side by side stay apart.
+fn pipe_at(cells: &HashMap<(i32, i32), char>, used: &HashSet<(i32, i32)>, start: (i32, i32)) -> Option<(i32, i32, Item)> {
+    let (col, row) = start;
+    let c = cells[&start];
+    let is_free = |cell: &(i32, i32)| cells.get(cell) == Some(&c) && !used.contains(cell);
+    let mut low = row;
+    while is_free(&(col, low-1)) && is_free(&(col+1, low-1)) {
+        low -= 1;
+    }
+
+    let mut candidates: Vec<(i32, i32, Item)> = vec![];
+    if c == 'P' {
+        candidates.push((col, row, Item::Pipe{len: (row-low) as usize, warp: None}));
+    }else {
+        // a side pipe going up starts two tiles left of that body, one that
+        // doesn't is only its opening
+        if row-low >= 2 {
+            candidates.push((col-2, low, Item::SidePipe{len: (row-low-1) as usize, warp: None}));
+        }
+        candidates.push((col, row-1, Item::SidePipe{len: 0, warp: None}));
+    }
+    candidates.into_iter().find(|(col, row, item)| entry("", 0, *col, *row, item.clone()).cells().iter().all(is_free))
+}
+
+fn parse_grid(area: &str, rows: &[(usize, String)], entries: &mut Vec<Entry>) -> Vec<(usize, String)> {
+    let mut errors: Vec<(usize, String)> = vec![];
+
+    let top = rows.len() as i32-1;
+    let mut cells: HashMap<(i32, i32), char> = HashMap::new();
+    let mut lines: HashMap<i32, usize> = HashMap::new();
+    for (index, (line, text)) in rows.iter().enumerate() {
+        let row = top-index as i32;
+        lines.insert(row, *line);
+        for (col, c) in text.chars().enumerate() {
+            if c != '.' && c != ' ' {
+                cells.insert((col as i32, row), c);
+            }
+        }
+    }
+
+    // top to bottom, left to right, the way the map reads
+    let mut order: Vec<(i32, i32)> = cells.keys().cloned().collect();
+    order.sort_by_key(|&(col, row)| (-row, col));
+
+    // flags and pipes span several tiles, find them first
+    let mut used: HashSet<(i32, i32)> = HashSet::new();
+    for &(col, row) in order.iter() {
+        if used.contains(&(col, row)) {
+            continue;
+        }
+        let line = lines[&row];
+        match cells[&(col, row)] {
+            'F' => {
+                used.insert((col, row));
+                for r in row+1..=row+FLAG_HEIGHT {
+                    if cells.get(&(col, r)) != Some(&'|') {
+                        errors.push((line, format!("flag at {} {} needs a pole {} tiles high", col, row, FLAG_HEIGHT)));
+                        break;
+                    }
+                    used.insert((col, r));
+                }
+                entries.push(entry(area, line, col, row, Item::Flag));
+            },
+            c @ ('P' | 'S') => {
+                let (pipe_col, pipe_row, item) = match pipe_at(&cells, &used, (col, row)) {
+                    Some(pipe) => pipe,
+                    None => {
+                        // one error for the whole lot of them
+                        used.extend(flood(&cells, (col, row)));
+                        let name = if c == 'P' {"pipe"} else {"sidepipe"};
+                        errors.push((line, format!("`{}` tiles at {} {} don't form a {}", c, col, row, name)));
+                        continue;
+                    },
+                };
+                let pipe = entry(area, lines[&pipe_row], pipe_col, pipe_row, item);
+                used.extend(pipe.cells());
+                entries.push(pipe);
+            },
+            _ => {},
+        }
+    }
+
+    for &(col, row) in order.iter() {
+        if used.contains(&(col, row)) {
+            continue;
+        }
+        let line = lines[&row];
+        let c = cells[&(col, row)];
+
+        // rows of the same tile become one entry
+        let is_run_start = cells.get(&(col-1, row)) != Some(&c);
+        let mut to_col = col;
+        while cells.get(&(to_col+1, row)) == Some(&c) {
+            to_col += 1;
+        }
+
+        let item = match c {
+            '=' | '~' => {
+                let name = if c == '=' {"floor"} else {"lava"};
+                if row > 1 {
+                    errors.push((line, format!("{} at {} {} is not on the bottom two rows", name, col, row)));
+                    continue;
+                }
+                if cells.get(&(col, 1-row)) != Some(&c) {
+                    errors.push((line, format!("{} at {} {} needs both bottom rows", name, col, row)));
+                    continue;
+                }
+                if row == 1 || !is_run_start {
+                    continue;
+                }
+                if c == '=' {Item::Floor{to_col}} else {Item::Lava{to_col}}
+            },
+            'B' | '#' | 'o' | '-' if !is_run_start => continue,
+            'B' => Item::Bricks{to_col, to_row: row, content: "".to_string(), count: 0},
+            '#' => Item::Stones{to_col, to_row: row},
+            'o' => Item::Coins{to_col, to_row: row},
+            '-' => Item::Bridge{to_col},
+            'b' => Item::Bricks{to_col: col, to_row: row, content: "block".to_string(), count: 1},
+            '*' => Item::Bricks{to_col: col, to_row: row, content: "star".to_string(), count: 1},
+            '?' => Item::Question{content: "coin".to_string()},
+            'M' => Item::Question{content: "mushroom".to_string()},
+            'U' => Item::Question{content: "1up".to_string()},
+            'g' => Item::Goomba,
+            'k' => Item::Troopa,
+            'c' => Item::Castle{size: "small".to_string()},
+            'C' => Item::Castle{size: "large".to_string()},
+            'A' => Item::Axe,
+            'x' => Item::Firebar{len: FIREBAR_LEN, clockwise: true},
+            'W' => Item::Bowser,
+            'q' => Item::Blooper,
+            'f' => Item::Cheep,
+            '|' => {
+                errors.push((line, format!("flag pole at {} {} has no flag", col, row)));
+                continue;
+            },
+            _ => {
+                errors.push((line, format!("unknown tile `{}` at {} {}", c, col, row)));
+                continue;
+            },
+        };
+        entries.push(entry(area, line, col, row, item));
+    }
+
+    errors
+}
+
+fn apply_note(entries: &mut [Entry], area: &str, tokens: &[&str]) -> Result<(), String> {
+    let keyword = tokens[0];
+    let (usage, count) = match keyword {
+        "warp" => ("<col> <row> <area> <to_col> <to_row>", 6),
+        "firebar" => ("<col> <row> <len> <cw | ccw>", 5),
+        _ => ("<col> <row> <n>", 4),
+    };
+    if tokens.len() != count {
+        return Err(format!("`{}` expects {}", keyword, usage));
+    }
+    let col: i32 = level::number(tokens[1])?;
+    let row: i32 = level::number(tokens[2])?;
+
+    let found = entries.iter_mut().find(|entry| entry.area == area && entry.col == col && entry.row == row);
+    match (keyword, found.map(|entry| &mut entry.item)) {
+        ("warp", Some(Item::Pipe{warp, ..} | Item::SidePipe{warp, ..})) => {
+            *warp = level::parse_warp(&["warp", tokens[3], tokens[4], tokens[5]])?;
+        },
+        ("warp", _) => return Err(format!("no pipe at {} {}", col, row)),
+        ("firebar", Some(Item::Firebar{len, clockwise})) => {
+            *len = level::number(tokens[3])?;
+            *clockwise = level::parse_turn(tokens[4])?;
+        },
+        ("firebar", _) => return Err(format!("no firebar at {} {}", col, row)),
+        (_, Some(Item::Bricks{content, count, ..})) if content == "block" => {
+            *count = level::number(tokens[3])?;
+            if *count == 0 {
+                return Err("a coin brick holds at least one coin".to_string());
+            }
+        },
+        _ => return Err(format!("no coin brick at {} {}", col, row)),
+    }
+    Ok(())
+}
+
+pub fn parse(file: &str, src: &str) -> Result<Level, Vec<LevelError>> {
+    let mut errors: Vec<(usize, String)> = vec![];
+
+    let mut level = Level::new(file);
+    let mut area = "overworld".to_string();
+    let mut grid: Option<Vec<(usize, String)>> = None;
+    let mut notes: Vec<(usize, String, String)> = vec![];
+
+    for (index, text) in src.lines().enumerate() {
+        let line = index+1;
+
+        if let Some(rows) = grid.as_mut() {
+            if text.trim() == "end" {
+                errors.extend(parse_grid(&area, rows, &mut level.entries));
+                grid = None;
+            }else {
+                rows.push((line, text.trim_end().to_string()));
+            }
+            continue;
+        }
+
+        let text = text.split('#').next().unwrap();
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        let (keyword, args) = (tokens[0], &tokens[1..]);
+
+        let result = match keyword {
+            "name" | "time" | "screens" | "start" | "checkpoint" | "area" | "music" => level::parse_header(keyword, args, &mut level, &mut area),
+            "map" if args.is_empty() => {
+                grid = Some(vec![]);
+                Ok(())
+            },
+            "map" => Err("`map` takes no arguments".to_string()),
+            // pipes and bricks may come later in the grid, notes are applied at the end
+            "warp" | "count" | "firebar" => {
+                notes.push((line, area.clone(), text.to_string()));
+                Ok(())
+            },
+            _ => Err(format!("unknown entry `{}`", keyword)),
+        };
+
+        if let Err(message) = result {
+            errors.push((line, message));
+        }
+    }
+
+    if grid.is_some() {
+        errors.push((0, "`map` without `end`".to_string()));
+    }
+
+    for (line, area, text) in notes.iter() {
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        if let Err(message) = apply_note(&mut level.entries, area, &tokens) {
+            errors.push((*line, message));
+        }
+    }
+
+    errors.extend(level.check());
+
+    if !errors.is_empty() {
+        return Err(errors.into_iter().map(|(line, message)| LevelError{file: file.to_string(), line, message}).collect());
+    }
+
+    Ok(level)
+}
+
+// The tiles an entry is drawn with.
+fn tiles(entry: &Entry) -> Vec<((i32, i32), char)> {
+    let c = match &entry.item {
+        Item::Floor{..} => '=',
+        Item::Bricks{count: 0, ..} => 'B',
+        Item::Bricks{content, ..} if content == "star" => '*',
+        Item::Bricks{..} => 'b',
+        Item::Question{content} if content == "mushroom" => 'M',
+        Item::Question{content} if content == "1up" => 'U',
+        Item::Question{..} => '?',
+        Item::Stones{..} | Item::Stairs{..} => '#',
+        Item::Pipe{..} => 'P',
+        Item::SidePipe{..} => 'S',
+        Item::Coins{..} => 'o',
+        Item::Goomba => 'g',
+        Item::Troopa => 'k',
+        Item::Lava{..} => '~',
+        Item::Bridge{..} => '-',
+        Item::Axe => 'A',
+        Item::Firebar{..} => 'x',
+        Item::Blooper => 'q',
+        Item::Cheep => 'f',
+        Item::Bowser => return vec![((entry.col, entry.row), 'W')],
+        Item::Flag => return entry.cells().into_iter().map(|cell| (cell, if cell.1 == entry.row {'F'} else {'|'})).collect(),
+        Item::Castle{size} if size == "large" => return vec![((entry.col, entry.row), 'C')],
+        Item::Castle{..} => return vec![((entry.col, entry.row), 'c')],
+    };
+    entry.cells().into_iter().map(|cell| (cell, c)).collect()
+}
+
+// Draws a level as a tile map, fails if two entries need the same tile.
+pub fn export(level: &Level) -> Result<String, String> {
+    let mut out = format!("name {}\ntime {}\nscreens {}\nstart {} {}\n", level.name, level.time, level.screens, level.start.0, level.start.1);
+    if let Some((col, row)) = level.checkpoint {
+        out += &format!("checkpoint {} {}\n", col, row);
+    }
+
+    for area in level.areas.iter() {
+        let mut cells: HashMap<(i32, i32), char> = HashMap::new();
+        let mut notes: Vec<((i32, i32), String)> = vec![];
+
+        for entry in level.entries_in(&area.name) {
+            for ((col, row), c) in tiles(entry) {
+                if cells.insert((col, row), c).is_some() {
+                    return Err(format!("line {}: `{}` at {} {} is drawn over another entry", entry.line, entry.name(), col, row));
+                }
+            }
+
+            match &entry.item {
+                Item::Pipe{warp: Some(warp), ..} | Item::SidePipe{warp: Some(warp), ..} => {
+                    notes.push(((entry.col, entry.row), format!("warp {} {} {} {} {}", entry.col, entry.row, warp.area, warp.col, warp.row)));
+                },
+                Item::Firebar{len, clockwise} if *len != FIREBAR_LEN || !clockwise => {
+                    let turn = if *clockwise {"cw"} else {"ccw"};
+                    notes.push(((entry.col, entry.row), format!("firebar {} {} {} {}", entry.col, entry.row, len, turn)));
+                },
+                Item::Bricks{content, count, ..} if content == "block" && *count != 1 => {
+                    for (col, row) in entry.cells() {
+                        notes.push(((col, row), format!("count {} {} {}", col, row, count)));
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        if cells.is_empty() {
+            continue;
+        }
+
+        let cols = cells.keys().map(|cell| cell.0+1).max().unwrap();
+        let width = (cols+level::SCREEN_COLS-1)/level::SCREEN_COLS*level::SCREEN_COLS;
+        let rows = cells.keys().map(|cell| cell.1+1).max().unwrap().max(MIN_ROWS);
+
+        out += &format!("\narea {}", area.name);
+        if area.style != area.name {
+            out += &format!(" {}", area.style);
+        }
+        if area.music != area.style {
+            out += &format!("\nmusic {}", area.music);
+        }
+        out += "\nmap\n";
+        for row in (0..rows).rev() {
+            let line: String = (0..width).map(|col| *cells.get(&(col, row)).unwrap_or(&'.')).collect();
+            out += &line;
+            out += "\n";
+        }
+        out += "end\n";
+
+        notes.sort_by_key(|&((col, row), _)| (-row, col));
+        for (_, note) in notes.iter() {
+            out += note;
+            out += "\n";
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipes(map: &str) -> Result<Vec<(i32, i32, usize)>, Vec<LevelError>> {
+        let level = parse("test", &format!("screens 1\nstart 1 2\nmap\n{}end\n", map))?;
+        Ok(level.entries.iter().filter_map(|entry| match entry.item {
+            Item::Pipe{len, ..} => Some((entry.col, entry.row, len)),
+            _ => None,
+        }).collect())
+    }
+
+    #[test]
+    fn pipes_side_by_side_stay_apart() {
+        let map = "
+....PP..........
+..PPPP..........
+..PPPP..........
+================
+================
+";
+        let mut found = pipes(map).ok().unwrap();
+        found.sort();
+        assert!(found == vec![(2, 3, 1), (4, 4, 2)]);
+    }
+
+    #[test]
+    fn half_a_pipe_is_an_error() {
+        let map = "
+..PPP...........
+..PPP...........
+================
+================
+";
+        assert!(pipes(map).is_err());
+    }
+}
@@ -1,82 +1,12 @@
 use crate::scenes::game;
 use crate::scenes::game::background;
+use crate::scenes::game::collision::{Collider, Grid, Hitbox};
+use crate::scenes::game::level;
 use crate::scenes::game::objects;
 use std::path::Path;
 
-pub struct TileUnderground {
-    pub floor: Vec<game::Block>,
-    pub wall: Vec<game::Block>,
-    pub pipe: objects::Pipe,
-    pub objects: objects::Objects,
-    pub delay: i32,
-}
-
-impl TileUnderground {
-    fn create() -> Self {
-        let mut floor: Vec<game::Block> = vec![];
-
-        for j in (1..=3).step_by(2)  {
-            for i in (1..=32).step_by(2) {
-                let stone = game::Block::create(
-                    -1.0+((16.0/256.0)*i as f32), 
-                    -1.0-((16.0/240.0)*(27+j) as f32), 
-                    16.0/240.0, 
-                    16.0/256.0,
-                    false,
-                    &Path::new("src/scenes/game/assets/images/stone_underground.png"),
-                    "block",
-                );
-                floor.push(stone);
-            }
-        }
-
-        let mut wall: Vec<game::Block> = vec![];
-
-        for i in (6..=26).rev().step_by(2) {
-            let brick = game::Block::create(
-                -1.0+(16.0/256.0), 
-                -1.0-((16.0/240.0)*i as f32), 
-                16.0/240.0, 
-                16.0/256.0,
-                false,
-                &Path::new("src/scenes/game/assets/images/brick_underground.png"),
-                "block",
-            );
-            wall.push(brick);
-        }
-
-        // TODO REFACTORIZE CODE
-        let pipe = objects::Pipe::create_sidepipe(
-            -1.0+((16.0/256.0)*27 as f32),
-            -1.0-((16.0/240.0)*25 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            9,
-        );
-        
-        let objects = objects::Objects::init();
-        let delay = 0;
-
-        Self{floor, wall, pipe, objects, delay}
-    }
-
-    unsafe fn draw(&self) {
-        for stone in self.floor.iter() {
-            stone.draw()
-        }
-
-        for brick in self.wall.iter() {
-            brick.draw();
-        }
-
-        self.pipe.draw();
-
-        self.objects.draw();
-    }
-}
-
 pub struct Tile {
-    pub bg: background::Background,
+    pub bg: Option<background::Background>,
     pub floor: Vec<game::Block>,
     pub objects: objects::Objects,
     move_by: f32,
@@ -85,8 +15,19 @@ pub struct Tile {
 }
 
 impl Tile {
-    fn create(last_pos: i32, add: i32, mut bg_index: u32, mut move_by: f32, floor_hole: Vec<i32>) -> Self {
-        let bg = background::Background::init(move_by, -1.0+((16.0/240.0)*13 as f32), 208.0/240.0, 1.0, bg_index); 
+    fn create(last_pos: i32, add: i32, mut bg_index: u32, mut move_by: f32, floor_hole: Vec<i32>, style: &str) -> Self {
+        // only the overworld has hills and bushes behind it
+        let bg = if style == "overworld" {
+            Some(background::Background::init(move_by, -1.0+((16.0/240.0)*13.0), 208.0/240.0, 1.0, bg_index))
+        }else {
+            None
+        };
+        let floor_path = match style {
+            "overworld" => "src/scenes/game/assets/images/stone.png",
+            "underground" => "src/scenes/game/assets/images/stone_underground.png",
+            "water" => "src/scenes/game/assets/images/stone_water.png",
+            _ => "src/scenes/game/assets/images/stone_castle.png",
+        };
 
         // Floor
         let mut floor: Vec<game::Block> = vec![];
@@ -96,7 +37,7 @@ impl Tile {
         let mut block_index = 1;
         for j in (1..=3).step_by(2)  {
             for i in (last_pos..=32*add).step_by(2) {
-                if floor_hole.len() != 0 && floor_hole.len() != counter && floor_hole[counter] == block_index {
+                if !floor_hole.is_empty() && floor_hole.len() != counter && floor_hole[counter] == block_index {
                     counter += 1;
                 }else{
                     let stone = game::Block::create(
@@ -105,7 +46,7 @@ impl Tile {
                         16.0/240.0, 
                         16.0/256.0,
                         false,
-                        &Path::new("src/scenes/game/assets/images/stone.png"),
+                        Path::new(floor_path),
                         "block",
                     );
                     floor.push(stone);
@@ -125,552 +66,238 @@ impl Tile {
 
         Self{bg, floor, objects, move_by, bg_index, last_drawpos}
     }
+}
 
-    unsafe fn draw(&self) {
-        self.bg.background_prog.set_active();
-        self.bg.draw();
-
-        self.floor[0].program.set_active();
-        for stone in self.floor.iter() {
-            stone.draw()
-        }
-
-        self.objects.draw()
-    }
+// What a solid in the world's collision grid is, blocks can be bumped so
+// they keep where to find them.
+#[derive(Clone, Copy)]
+pub enum Solid {
+    Ground,
+    Block{tile: usize, index: usize},
+    Question{tile: usize, index: usize},
+    // the bridge over the lava, it falls once the axe is taken
+    Bridge,
 }
 
+// One area of a level, the simulation only keeps the area Mario is in.
 pub struct World {
     pub tiles: Vec<Tile>,
-    pub tiles_underground: Vec<TileUnderground>,
+    pub solids: Grid<Solid>,
     pub bg_color: String,
+    pub screens: i32,
+    pub is_water: bool,
+    pub delay: i32,
+    // the tile and index of every flag, they never move
+    pub flags: Vec<(usize, usize)>,
+    // where the door of the first castle is, if the area has one
+    pub castle_x: Option<f32>,
+    // what hurts or ends the level without being solid, looked up around
+    // Mario; the axes keep which tile they're in
+    pub lava: Grid<()>,
+    pub axes: Grid<usize>,
+    // the tile and hitbox of every bridge piece from left to right, the
+    // last one falls first
+    pub bridge: Vec<(usize, Hitbox)>,
+    pub firebars: Vec<(usize, usize)>,
+    // the tile and index of the pipes that lead somewhere
+    pub warps: Vec<(usize, usize)>,
 }
 
 impl World {
-    pub fn init() -> Self {
+    pub fn init(level: &level::Level, area: &str) -> Self {
         let mut tiles: Vec<Tile> = vec![];
-        
-        let mut tiles_underground: Vec<TileUnderground> = vec![];
-        let mut tile_underground1 = TileUnderground::create();
-        for j in (1..=3).step_by(2)  {
-            for i in (9..=21).step_by(2) {
-                tile_underground1.objects.create_block(
-                    -1.0+((16.0/256.0)*i as f32), 
-                    -1.0-((16.0/240.0)*(23+j) as f32), 
-                    16.0/240.0, 
-                    16.0/256.0,
-                    false,
-                    "src/scenes/game/assets/images/brick_underground.png",
-                );
+        let style = level.area(area).style.clone();
+        let screens = level.screens_in(area);
+
+        let mut floor_cols: Vec<bool> = vec![false; (screens*level::SCREEN_COLS) as usize];
+        for entry in level.entries_in(area) {
+            if let level::Item::Floor{to_col} = entry.item {
+                for col in entry.col..=to_col {
+                    floor_cols[col as usize] = true;
+                }
             }
         }
-        for i in (9..=21).step_by(2) {
-            tile_underground1.objects.create_block(
-                -1.0+((16.0/256.0)*i as f32), 
-                -1.0-((16.0/240.0)*6 as f32), 
-                16.0/240.0, 
-                16.0/256.0,
-                false,
-                "src/scenes/game/assets/images/brick_underground.png",
-            );
-        }
-        for i in (9..=21).step_by(2) {
-            tile_underground1.objects.create_coin(
-                -1.0+((16.0/256.0)*i as f32), 
-                -1.0-((16.0/240.0)*22 as f32), 
-            );
-        }
-        for i in (9..=21).step_by(2) {
-            tile_underground1.objects.create_coin(
-                -1.0+((16.0/256.0)*i as f32), 
-                -1.0-((16.0/240.0)*18 as f32), 
-            );
-        }
-        for i in (11..=19).step_by(2) {
-            tile_underground1.objects.create_coin(
-                -1.0+((16.0/256.0)*i as f32), 
-                -1.0-((16.0/240.0)*14 as f32), 
-            );
-        }
-        tiles_underground.push(tile_underground1);
-
-        let floor_hole: Vec<i32> = vec![]; 
-
-        let tile1 = Tile::create(1, 1, 1, 0.0, floor_hole);
-        tiles.push(tile1);
-        
-        let floor_hole: Vec<i32> = vec![]; 
-
-        let mut tile2 = Tile::create(tiles[0].last_drawpos+2, 2, tiles[0].bg_index, tiles[0].move_by, floor_hole);
-        tile2.objects.create_question_mark_block(
-            -1.0+((16.0/256.0)*33 as f32), 
-            -1.0+((16.0/240.0)*11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            true,
-            "coin".to_string(),
-        );
-        tile2.objects.create_block(
-            -1.0+((16.0/256.0)*41 as f32), 
-            -1.0+((16.0/240.0)*11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            true,
-            "src/scenes/game/assets/images/brick.png",
-        );
-        tile2.objects.create_question_mark_block(
-            -1.0+((16.0/256.0)*43 as f32), 
-            -1.0+((16.0/240.0)*11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            true,
-            "mushroom".to_string(),
-        );
-        tile2.objects.create_block(
-            -1.0+((16.0/256.0)*45 as f32), 
-            -1.0+((16.0/240.0)*11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            false,
-            "src/scenes/game/assets/images/brick.png",
-        );
-        tile2.objects.create_question_mark_block(
-            -1.0+((16.0/256.0)*45 as f32), 
-            -1.0+((16.0/240.0)*19 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            true,
-            "coin".to_string(),
-        );
-        tile2.objects.create_question_mark_block(
-            -1.0+((16.0/256.0)*47 as f32), 
-            -1.0+((16.0/240.0)*11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            true,
-            "coin".to_string(),
-        );
-        tile2.objects.create_block(
-            -1.0+((16.0/256.0)*49 as f32), 
-            -1.0+((16.0/240.0)*11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            false,
-            "src/scenes/game/assets/images/brick.png",
-        );
-        tile2.objects.create_pipe(
-            -1.0+((16.0/256.0)*58 as f32), 
-            -1.0+((16.0/240.0)*7 as f32),
-            16.0/240.0, 
-            32.0/256.0,
-            1,
-            true,
-            false,
-        );
-        tiles.push(tile2);
-
-        let floor_hole: Vec<i32> = vec![]; 
-
-        let mut tile3 = Tile::create(tiles[1].last_drawpos+2, 3, tiles[1].bg_index, tiles[1].move_by, floor_hole);
-        tile3.objects.create_pipe(
-            -1.0+((16.0/256.0)*78 as f32), 
-            -1.0+((16.0/240.0)*9 as f32),
-            16.0/240.0, 
-            32.0/256.0,
-            2,
-            true,
-            false,
-        );
-        tile3.objects.create_pipe(
-            -1.0+((16.0/256.0)*94 as f32), 
-            -1.0+((16.0/240.0)*11 as f32),
-            16.0/240.0, 
-            32.0/256.0,
-            3,
-            true,
-            false,
-        );
-        tiles.push(tile3);
-
-        let floor_hole: Vec<i32> = vec![]; 
 
-        let mut tile4 = Tile::create(tiles[2].last_drawpos+2, 4, tiles[2].bg_index, tiles[2].move_by, floor_hole);
-        tile4.objects.create_pipe(
-            -1.0+((16.0/256.0)*116 as f32), 
-            -1.0+((16.0/240.0)*11 as f32),
-            16.0/240.0, 
-            32.0/256.0,
-            3,
-            true,
-            true,
-        );
-        tiles.push(tile4);
-
-        let floor_hole: Vec<i32> = vec![5, 6, 21, 22]; 
-
-        let mut tile5 = Tile::create(tiles[3].last_drawpos+2, 5, tiles[3].bg_index, tiles[3].move_by, floor_hole);
-        tile5.objects.create_block(
-            -1.0+((16.0/256.0)*155 as f32), 
-            -1.0+((16.0/240.0)*11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            false,
-            "src/scenes/game/assets/images/brick.png",
-        );
-        tile5.objects.create_question_mark_block(
-            -1.0+((16.0/256.0)*157 as f32), 
-            -1.0+((16.0/240.0)*11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            true,
-            "mushroom".to_string(),
-        );
-        tile5.objects.create_block(
-            -1.0+((16.0/256.0)*159 as f32), 
-            -1.0+((16.0/240.0)*11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            false,
-            "src/scenes/game/assets/images/brick.png",
-        );
-        tiles.push(tile5);
-
-        let floor_hole: Vec<i32> = vec![7, 8, 9, 23, 24, 25];
+        let mut last_pos = 1;
+        let mut bg_index = 1;
+        let mut move_by = 0.0;
+        for add in 1..=screens {
+            // the floor is laid out two rows at a time, holes are counted from 1
+            let first_col = (add-1)*level::SCREEN_COLS;
+            let mut floor_hole: Vec<i32> = vec![];
+            for row in 0..2 {
+                for i in 0..level::SCREEN_COLS {
+                    if !floor_cols[(first_col+i) as usize] {
+                        floor_hole.push(row*level::SCREEN_COLS+i+1);
+                    }
+                }
+            }
 
-        let mut tile6 = Tile::create(tiles[4].last_drawpos+2, 6, tiles[4].bg_index, tiles[4].move_by, floor_hole);
-        for i in (0..=7*2).step_by(2) {
-            tile6.objects.create_block(
-                -1.0+((16.0/256.0)*((161+i) as f32)), 
-                -1.0+((16.0/240.0)*19 as f32),
-                16.0/240.0, 
-                16.0/256.0,
-                false,
-                "src/scenes/game/assets/images/brick.png",
-            );
-        }
-        for i in (0..=2*2).step_by(2) {
-            tile6.objects.create_block(
-                -1.0+((16.0/256.0)*((183+i) as f32)), 
-                -1.0+((16.0/240.0)*19 as f32),
-                16.0/240.0, 
-                16.0/256.0,
-                false,
-                "src/scenes/game/assets/images/brick.png",
-            );
+            let tile = Tile::create(last_pos, add, bg_index, move_by, floor_hole, &style);
+            last_pos = tile.last_drawpos+2;
+            bg_index = tile.bg_index;
+            move_by = tile.move_by;
+            tiles.push(tile);
         }
-        tile6.objects.create_question_mark_block(
-            -1.0+((16.0/256.0)*189 as f32), 
-            -1.0+((16.0/240.0)*19 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            true,
-            "coin".to_string(),
-        );
-        tile6.objects.create_block(
-            -1.0+((16.0/256.0)*(189 as f32)), 
-            -1.0+((16.0/240.0)*11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            true,
-            "src/scenes/game/assets/images/brick.png",
-        );
-        tile6.objects.blocks[11].collision_num = 10;
-        tiles.push(tile6);
 
-        let floor_hole: Vec<i32> = vec![];
-
-        let mut tile7 = Tile::create(tiles[5].last_drawpos+2, 7, tiles[5].bg_index, tiles[5].move_by, floor_hole);
-        tile7.objects.create_block(
-            -1.0+(16.0/256.0)*(201 as f32), 
-            -1.0+(16.0/240.0)*(11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            false,
-            "src/scenes/game/assets/images/brick.png",
-        );
-        tile7.objects.create_block(
-            -1.0+(16.0/256.0)*(203 as f32), 
-            -1.0+(16.0/240.0)*(11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            true,
-            "src/scenes/game/assets/images/brick.png",
-        );
-        tile7.objects.blocks[1].collision_name = "star".to_string();
-        tile7.objects.create_question_mark_block(
-            -1.0+(16.0/256.0)*(213 as f32), 
-            -1.0+(16.0/240.0)*(11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            true,
-            "mushroom".to_string(),
-        );
-        tile7.objects.create_question_mark_block(
-            -1.0+(16.0/256.0)*(219 as f32), 
-            -1.0+(16.0/240.0)*(11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            true,
-            "coin".to_string(),
-        );
-        tile7.objects.create_question_mark_block(
-            -1.0+(16.0/256.0)*(219 as f32), 
-            -1.0+(16.0/240.0)*(19 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            true,
-            "coin".to_string(),
-        );
-        tiles.push(tile7);
-
-        let floor_hole: Vec<i32> = vec![];
-
-        let mut tile8 = Tile::create(tiles[6].last_drawpos+2, 8, tiles[6].bg_index, tiles[6].move_by, floor_hole);
-        tile8.objects.create_question_mark_block(
-            -1.0+(16.0/256.0)*(225 as f32), 
-            -1.0+(16.0/240.0)*(11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            true,
-            "coin".to_string(),
-        );
-        tile8.objects.create_block(
-            -1.0+(16.0/256.0)*(237 as f32), 
-            -1.0+(16.0/240.0)*(11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            false,
-            "src/scenes/game/assets/images/brick.png",
-        );
-        for i in (0..=2*2).step_by(2) {
-            tile8.objects.create_block(
-                -1.0+(16.0/256.0)*((243+i) as f32), 
-                -1.0+(16.0/240.0)*(19 as f32),
-                16.0/240.0, 
-                16.0/256.0,
-                false,
-                "src/scenes/game/assets/images/brick.png",
-            );
+        for entry in level.entries_in(area) {
+            let index = (entry.col/level::SCREEN_COLS).clamp(0, screens-1) as usize;
+            World::add_entry(&mut tiles[index].objects, entry, &style);
         }
-        tiles.push(tile8);
 
-        let floor_hole: Vec<i32> = vec![];
-
-        let mut tile9 = Tile::create(tiles[7].last_drawpos+2, 9, tiles[7].bg_index, tiles[7].move_by, floor_hole);
-        tile9.objects.create_block(
-            -1.0+(16.0/256.0)*(257 as f32), 
-            -1.0+(16.0/240.0)*(19 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            false,
-            "src/scenes/game/assets/images/brick.png",
-        );
-        tile9.objects.create_question_mark_block(
-            -1.0+(16.0/256.0)*(259 as f32), 
-            -1.0+(16.0/240.0)*(19 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            true,
-            "coin".to_string(),
-        );
-        tile9.objects.create_question_mark_block(
-            -1.0+(16.0/256.0)*(261 as f32), 
-            -1.0+(16.0/240.0)*(19 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            true,
-            "coin".to_string(),
-        );
-        tile9.objects.create_block(
-            -1.0+(16.0/256.0)*(263 as f32), 
-            -1.0+(16.0/240.0)*(19 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            false,
-            "src/scenes/game/assets/images/brick.png",
-        );
-        tile9.objects.create_block(
-            -1.0+(16.0/256.0)*(259 as f32), 
-            -1.0+(16.0/240.0)*(11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            false,
-            "src/scenes/game/assets/images/brick.png",
-        );
-        tile9.objects.create_block(
-            -1.0+(16.0/256.0)*(261 as f32), 
-            -1.0+(16.0/240.0)*(11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            false,
-            "src/scenes/game/assets/images/brick.png",
-        );
-        for i in (0..=(3*2)).step_by(2) {
-            for j in (0..=3*2-i).step_by(2) {
-                tile9.objects.create_stone(
-                    -1.0+(16.0/256.0)*((275-i) as f32), 
-                    -1.0+(16.0/240.0)*((5+j) as f32),
-                    16.0/240.0, 
-                    16.0/256.0,
-                );
+        let mut solids: Grid<Solid> = Grid::new();
+        for (tile_index, tile) in tiles.iter().enumerate() {
+            for brick in tile.floor.iter().chain(tile.objects.stones.iter()) {
+                solids.insert(brick.hitbox(), Solid::Ground);
             }
-        }
-        for i in (0..=(3*2)).step_by(2) {
-            for j in (0..=3*2-i).step_by(2) {
-                tile9.objects.create_stone(
-                    -1.0+(16.0/256.0)*((281+i) as f32), 
-                    -1.0+(16.0/240.0)*((5+j) as f32),
-                    16.0/240.0, 
-                    16.0/256.0,
-                );
+            for pipe in tile.objects.pipes.iter() {
+                for segment in pipe.objects.iter() {
+                    solids.insert(segment.hitbox(), Solid::Ground);
+                }
             }
-        }
-        tiles.push(tile9);
-
-        let floor_hole: Vec<i32> = vec![10, 11, 26, 27];
-
-        let mut tile10 = Tile::create(tiles[8].last_drawpos+2, 10, tiles[8].bg_index, tiles[8].move_by, floor_hole);
-        for i in (0..=3*2).step_by(2) {
-            tile10.objects.create_stone(
-                -1.0+(16.0/256.0)*(305 as f32), 
-                -1.0+(16.0/240.0)*((5+i) as f32),
-                16.0/240.0, 
-                16.0/256.0,
-            );
-        }
-        for i in (0..=(3*2)).step_by(2) {
-            for j in (0..=3*2-i).step_by(2) {
-                tile10.objects.create_stone(
-                    -1.0+(16.0/256.0)*((303-i) as f32), 
-                    -1.0+(16.0/240.0)*((5+j) as f32),
-                    16.0/240.0, 
-                    16.0/256.0,
-                );
+            for flag in tile.objects.flag.iter() {
+                solids.insert(flag.stone.hitbox(), Solid::Ground);
             }
-        }
-
-        for i in (0..=(3*2)).step_by(2) {
-            for j in (0..=3*2-i).step_by(2) {
-                tile10.objects.create_stone(
-                    -1.0+(16.0/256.0)*((311+i) as f32), 
-                    -1.0+(16.0/240.0)*((5+j) as f32),
-                    16.0/240.0, 
-                    16.0/256.0,
-                );
+            for bridge in tile.objects.bridge.iter() {
+                solids.insert(bridge.hitbox(), Solid::Bridge);
+            }
+            for (index, block) in tile.objects.blocks.iter().enumerate() {
+                solids.insert(block.hitbox(), Solid::Block{tile: tile_index, index});
+            }
+            for (index, block) in tile.objects.question_mark_blocks.iter().enumerate() {
+                solids.insert(block.hitbox(), Solid::Question{tile: tile_index, index});
             }
         }
-        tiles.push(tile10);
-
-        let floor_hole: Vec<i32> = vec![];
-
-        let mut tile11 = Tile::create(tiles[9].last_drawpos+2, 11, tiles[9].bg_index, tiles[9].move_by, floor_hole);
-        tile11.objects.create_pipe(
-            -1.0+(16.0/256.0)*(328 as f32), 
-            -1.0+(16.0/240.0)*(7 as f32),
-            16.0/240.0, 
-            32.0/256.0,
-            1,
-            true,
-            false,
-        );
-        tile11.objects.create_block(
-            -1.0+(16.0/256.0)*(337 as f32), 
-            -1.0+(16.0/240.0)*(11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            false,
-            "src/scenes/game/assets/images/brick.png",
-        );
-        tile11.objects.create_block(
-            -1.0+(16.0/256.0)*(339 as f32), 
-            -1.0+(16.0/240.0)*(11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            false,
-            "src/scenes/game/assets/images/brick.png",
-        );
-        tile11.objects.create_question_mark_block(
-            -1.0+(16.0/256.0)*(341 as f32), 
-            -1.0+(16.0/240.0)*(11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            true,
-            "coin".to_string(),
-        );
-        tile11.objects.create_block(
-            -1.0+(16.0/256.0)*(343 as f32), 
-            -1.0+(16.0/240.0)*(11 as f32),
-            16.0/240.0, 
-            16.0/256.0,
-            false,
-            "src/scenes/game/assets/images/brick.png",
-        );
-        tiles.push(tile11);
 
-        let floor_hole: Vec<i32> = vec![];
-
-        let mut tile12 = Tile::create(tiles[10].last_drawpos+2, 12, tiles[10].bg_index, tiles[10].move_by, floor_hole);
-        tile12.objects.create_pipe(
-            -1.0+(16.0/256.0)*(360 as f32), 
-            -1.0+(16.0/240.0)*(7 as f32),
-            16.0/240.0, 
-            32.0/256.0,
-            1,
-            true,
-            false,
-        );
-        for i in (0..=7*2).step_by(2) {
-            tile12.objects.create_stone(
-                -1.0+(16.0/256.0)*(379 as f32), 
-                -1.0+(16.0/240.0)*((5+i) as f32),
-                16.0/240.0, 
-                16.0/256.0,
-            );
-        }
-        for i in (0..=(8*2)).step_by(2) {
-            for j in (0..=7*2-i).step_by(2) {
-                tile12.objects.create_stone(
-                    -1.0+(16.0/256.0)*((377-i) as f32), 
-                    -1.0+(16.0/240.0)*((5+j) as f32),
-                    16.0/240.0, 
-                    16.0/256.0,
-                );
+        let flags: Vec<(usize, usize)> = tiles.iter().enumerate()
+            .flat_map(|(tile_index, tile)| (0..tile.objects.flag.len()).map(move |index| (tile_index, index)))
+            .collect();
+        let castle_x = tiles.iter().flat_map(|tile| tile.objects.castle.iter()).next().map(|castle| castle.x);
+
+        let mut lava: Grid<()> = Grid::new();
+        let mut axes: Grid<usize> = Grid::new();
+        let mut bridge: Vec<(usize, Hitbox)> = vec![];
+        let mut firebars: Vec<(usize, usize)> = vec![];
+        let mut warps: Vec<(usize, usize)> = vec![];
+        for (tile_index, tile) in tiles.iter().enumerate() {
+            for block in tile.objects.lava.iter() {
+                lava.insert(block.hitbox(), ());
+            }
+            for axe in tile.objects.axe.iter() {
+                axes.insert(axe.hitbox(), tile_index);
+            }
+            bridge.extend(tile.objects.bridge.iter().map(|piece| (tile_index, piece.hitbox())));
+            firebars.extend((0..tile.objects.firebars.len()).map(|index| (tile_index, index)));
+            for (index, pipe) in tile.objects.pipes.iter().enumerate() {
+                if pipe.warp.is_some() {
+                    warps.push((tile_index, index));
+                }
             }
         }
-        tiles.push(tile12);
+        bridge.sort_by(|a, b| a.1.x.total_cmp(&b.1.x));
 
-        let floor_hole: Vec<i32> = vec![];
+        let is_water = style == "water";
+        let bg_color = if style == "overworld" || is_water {"blue"} else {"black"}.to_string();
 
-        let mut tile13 = Tile::create(tiles[11].last_drawpos+2, 13, tiles[11].bg_index, tiles[11].move_by, floor_hole);
-        tile13.objects.create_flag(
-            -1.0+(16.0/256.0)*(397 as f32), 
-            -1.0+(16.0/240.0)*(5 as f32),
-        );
-        tile13.objects.create_castle(
-            -1.0+(16.0/256.0)*(409 as f32), 
-            -1.0+(16.0/240.0)*(9 as f32),
-            "small",
-        );
-        tiles.push(tile13);
-
-        let bg_color = "blue".to_string();
-
-        Self{tiles, tiles_underground, bg_color}
+        Self{tiles, solids, bg_color, screens, is_water, delay: 0, flags, castle_x, lava, axes, bridge, firebars, warps}
     }
 
-    pub unsafe fn draw(&self) {
-        unsafe {
-            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
-            if self.bg_color == "blue" {
-                gl::ClearColor(0.384, 0.671, 0.831, 1.0);
-            }
-            gl::Clear(gl::COLOR_BUFFER_BIT);
-        }
-        for tile in self.tiles.iter() {
-            tile.draw();
-        }
+    pub fn pipes(&self) -> impl Iterator<Item = &objects::Pipe> {
+        self.tiles.iter().flat_map(|tile| tile.objects.pipes.iter())
+    }
 
-        for tile in self.tiles_underground.iter() {
-            tile.draw();
+    fn add_entry(objects: &mut objects::Objects, entry: &level::Entry, style: &str) {
+        let brick_path = match style {
+            "underground" => "src/scenes/game/assets/images/brick_underground.png",
+            "castle" => "src/scenes/game/assets/images/brick_castle.png",
+            _ => "src/scenes/game/assets/images/brick.png",
+        };
+        // castles and the sea are built from the same blocks as their floor
+        let stone_path = match style {
+            "castle" => "src/scenes/game/assets/images/stone_castle.png",
+            "water" => "src/scenes/game/assets/images/stone_water.png",
+            _ => "src/scenes/game/assets/images/stone_up.png",
+        };
+
+        match &entry.item {
+            level::Item::Bricks{content, count, ..} => {
+                for (col, row) in entry.cells() {
+                    objects.create_block(level::tile_x(col), level::tile_y(row), level::TILE_H, level::TILE_W, *count > 0, brick_path);
+                    let block = objects.blocks.last_mut().unwrap();
+                    if *count > 0 {
+                        block.collision_name = content.clone();
+                        block.collision_num = *count;
+                    }
+                }
+            },
+            level::Item::Question{content} => {
+                objects.create_question_mark_block(
+                    level::tile_x(entry.col),
+                    level::tile_y(entry.row),
+                    level::TILE_H,
+                    level::TILE_W,
+                    true,
+                    content.clone(),
+                );
+            },
+            level::Item::Stones{..} | level::Item::Stairs{..} => {
+                for (col, row) in entry.cells() {
+                    objects.create_stone(level::tile_x(col), level::tile_y(row), level::TILE_H, level::TILE_W, stone_path);
+                }
+            },
+            level::Item::Pipe{len, warp} => {
+                let mut pipe = objects::Pipe::create(
+                    -1.0+level::TILE_W*(2*entry.col+2) as f32,
+                    level::tile_y(entry.row),
+                    level::TILE_H,
+                    2.0*level::TILE_W,
+                    *len,
+                    true,
+                    warp.is_some(),
+                );
+                pipe.warp = warp.clone();
+                objects.pipes.push(pipe);
+            },
+            level::Item::SidePipe{len, warp} => {
+                let mut pipe = objects::Pipe::create_sidepipe(
+                    level::tile_x(entry.col),
+                    level::tile_y(entry.row)+level::TILE_H,
+                    level::TILE_H,
+                    level::TILE_W,
+                    *len,
+                );
+                pipe.warp = warp.clone();
+                objects.pipes.push(pipe);
+            },
+            level::Item::Coins{..} => {
+                for (col, row) in entry.cells() {
+                    objects.create_coin(level::tile_x(col), level::tile_y(row));
+                }
+            },
+            level::Item::Flag => {
+                objects.create_flag(level::tile_x(entry.col), level::tile_y(entry.row));
+            },
+            level::Item::Castle{size} => {
+                objects.create_castle(level::tile_x(entry.col+2), level::tile_y(entry.row+2), size);
+            },
+            level::Item::Lava{..} => {
+                for (col, row) in entry.cells() {
+                    objects.create_lava(level::tile_x(col), level::tile_y(row), row == 1);
+                }
+            },
+            level::Item::Bridge{..} => {
+                for (col, row) in entry.cells() {
+                    objects.create_bridge(level::tile_x(col), level::tile_y(row));
+                }
+            },
+            level::Item::Axe => {
+                objects.create_axe(level::tile_x(entry.col), level::tile_y(entry.row));
+            },
+            level::Item::Firebar{len, clockwise} => {
+                objects.create_firebar(level::tile_x(entry.col), level::tile_y(entry.row), *len, *clockwise);
+            },
+            // the floor is part of the tiles, mobs are spawned by the simulation
+            level::Item::Floor{..} | level::Item::Goomba | level::Item::Troopa | level::Item::Bowser | level::Item::Blooper | level::Item::Cheep => {},
         }
     }
 }
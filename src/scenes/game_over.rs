@@ -0,0 +1,36 @@
+use crate::scenes::{Scene, Transition};
+use crate::scenes::game::{self, Input, Renderer, Status};
+use crate::scenes::title::Title;
+
+// how long the game over screen stays before the title comes back
+const GAME_OVER_STEPS: u32 = 240;
+
+pub struct GameOver {
+    status: Status,
+    delay: u32,
+}
+
+impl GameOver {
+    pub fn init(status: Status) -> Self {
+        Self{status, delay: GAME_OVER_STEPS}
+    }
+}
+
+impl Scene for GameOver {
+    fn handle_input(&mut self, _input: &Input) {}
+
+    fn update(&mut self) {
+        self.delay -= 1;
+    }
+
+    unsafe fn draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
+        renderer.draw_game_over(&self.status);
+    }
+
+    fn transition(&mut self) -> Transition {
+        if self.delay == 0 {
+            return Transition::Replace(Box::new(Title::init(game::LEVEL_PATH, None)));
+        }
+        Transition::None
+    }
+}
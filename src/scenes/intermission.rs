@@ -0,0 +1,36 @@
+use crate::scenes::{Scene, Transition};
+use crate::scenes::game::{Input, Renderer, Status};
+
+// how long the world and lives are shown before every try
+const INTERMISSION_STEPS: u32 = 150;
+
+// The world and Mario's lives on black, it pops back to the game by itself.
+pub struct Intermission {
+    status: Status,
+    delay: u32,
+}
+
+impl Intermission {
+    pub fn init(status: Status) -> Self {
+        Self{status, delay: INTERMISSION_STEPS}
+    }
+}
+
+impl Scene for Intermission {
+    fn handle_input(&mut self, _input: &Input) {}
+
+    fn update(&mut self) {
+        self.delay -= 1;
+    }
+
+    unsafe fn draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
+        renderer.draw_intermission(&self.status);
+    }
+
+    fn transition(&mut self) -> Transition {
+        if self.delay == 0 {
+            return Transition::Pop;
+        }
+        Transition::None
+    }
+}
@@ -0,0 +1,35 @@
+use crate::scenes::{Scene, Transition};
+use crate::scenes::game::{Input, Renderer};
+
+// Opened from the pause menu, it shows the controls until fire is pressed.
+pub struct Options {
+    held: bool,
+    is_back: bool,
+}
+
+impl Options {
+    pub fn init() -> Self {
+        // fire is still down from picking the menu item
+        Self{held: true, is_back: false}
+    }
+}
+
+impl Scene for Options {
+    fn handle_input(&mut self, input: &Input) {
+        self.is_back = input.fire && !self.held;
+        self.held = input.fire;
+    }
+
+    fn update(&mut self) {}
+
+    unsafe fn draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
+        renderer.draw_options();
+    }
+
+    fn transition(&mut self) -> Transition {
+        if self.is_back {
+            return Transition::Pop;
+        }
+        Transition::None
+    }
+}
@@ -0,0 +1,50 @@
+use crate::scenes::{Scene, Transition};
+use crate::scenes::game::{Game, Input, Renderer};
+
+// A new seed for every game that isn't a replay.
+fn clock_seed() -> u64 {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap();
+    now.as_nanos() as u64
+}
+
+// The title over the first screen of the level. Pressing fire or jump hands
+// the waiting game over, a key held since before the title doesn't count.
+pub struct Title {
+    game: Option<Game>,
+    held: bool,
+    is_start: bool,
+}
+
+impl Title {
+    pub fn init(level_path: &str, record_path: Option<String>) -> Self {
+        let game = Some(Game::init(level_path, clock_seed(), record_path));
+        Self{game, held: true, is_start: false}
+    }
+}
+
+impl Scene for Title {
+    fn handle_input(&mut self, input: &Input) {
+        let pressed = input.fire || input.up;
+        self.is_start = pressed && !self.held;
+        self.held = pressed;
+    }
+
+    fn update(&mut self) {}
+
+    unsafe fn draw(&mut self, renderer: &mut Renderer, _alpha: f32) {
+        if let Some(game) = self.game.as_mut() {
+            game.draw(renderer, 0.0);
+        }
+        renderer.draw_title();
+    }
+
+    fn transition(&mut self) -> Transition {
+        if !self.is_start {
+            return Transition::None;
+        }
+        match self.game.take() {
+            Some(game) => Transition::Replace(Box::new(game)),
+            None => Transition::None,
+        }
+    }
+}